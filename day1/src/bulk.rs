@@ -0,0 +1,148 @@
+use std::io::{self, Read};
+
+use crate::ReadingError;
+
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Parses readings straight out of a fixed-size byte buffer that is refilled
+/// from `reader`, so no line ever gets its own `String`.
+///
+/// Accepts the same input as `readings`: one integer per line with an
+/// optional leading `+`, surrounded by optional spaces, tabs or `\r`.
+pub(crate) struct BulkReadings<R> {
+    reader: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    line: usize,
+    column: usize,
+    eof: bool,
+}
+
+/// Progress through the line currently being parsed.
+#[derive(Default)]
+struct LineState {
+    value: u32,
+    digits: usize,
+    sign: bool,
+    trailing: bool,
+    seen_bytes: bool,
+    error: Option<ReadingError>,
+}
+
+impl<R: Read> BulkReadings<R> {
+    pub fn new(reader: R) -> Self {
+        BulkReadings {
+            reader,
+            buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            line: 1,
+            column: 0,
+            eof: false,
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        loop {
+            match self.reader.read(&mut self.buf) {
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                    self.eof = n == 0;
+                    return Ok(());
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn malformed(&self, reason: &'static str) -> ReadingError {
+        ReadingError::Malformed {
+            line: self.line,
+            column: self.column,
+            reason,
+        }
+    }
+
+    fn finish_line(&self, state: LineState) -> Result<u32, ReadingError> {
+        match state.error {
+            Some(err) => Err(err),
+            None if state.digits == 0 => Err(self.malformed("no digits")),
+            None => Ok(state.value),
+        }
+    }
+}
+
+impl<R: Read> Iterator for BulkReadings<R> {
+    type Item = Result<u32, ReadingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = LineState::default();
+        loop {
+            if self.pos == self.len {
+                if self.eof {
+                    return None;
+                }
+                if let Err(err) = self.refill() {
+                    self.eof = true;
+                    return Some(Err(ReadingError::Io {
+                        line: self.line,
+                        err,
+                    }));
+                }
+                if self.eof {
+                    // A last line without a trailing newline still counts.
+                    return state.seen_bytes.then(|| self.finish_line(state));
+                }
+            }
+
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+                self.column += 1;
+
+                if byte == b'\n' {
+                    let result = self.finish_line(state);
+                    self.line += 1;
+                    self.column = 0;
+                    return Some(result);
+                }
+
+                state.seen_bytes = true;
+                if state.error.is_some() {
+                    continue;
+                }
+
+                match byte {
+                    b'0'..=b'9' if state.trailing => {
+                        state.error = Some(self.malformed("whitespace inside number"));
+                    }
+                    b'0'..=b'9' => {
+                        let value = state
+                            .value
+                            .checked_mul(10)
+                            .and_then(|v| v.checked_add((byte - b'0') as u32));
+                        match value {
+                            Some(value) => {
+                                state.value = value;
+                                state.digits += 1;
+                            }
+                            None => state.error = Some(self.malformed("number too large")),
+                        }
+                    }
+                    b'+' if state.digits == 0 && !state.sign => state.sign = true,
+                    b' ' | b'\t' | b'\r' if state.sign && state.digits == 0 => {
+                        state.error = Some(self.malformed("whitespace after sign"));
+                    }
+                    b' ' | b'\t' | b'\r' => state.trailing = state.digits > 0,
+                    _ if !byte.is_ascii() => {
+                        state.error = Some(self.malformed("non-ASCII byte"));
+                    }
+                    _ => state.error = Some(self.malformed("unexpected byte")),
+                }
+            }
+        }
+    }
+}
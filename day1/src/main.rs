@@ -1,35 +1,382 @@
-use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead};
+use std::num::ParseIntError;
+use std::time::Instant;
 
-fn main() {
-    let mut count = 0;
-    let mut last_measure = None;
-    let mut window = VecDeque::new();
-    let data_iter = BufReader::new(File::open("aoc1.txt").expect("Can't open the file."))
-        .lines()
-        .map(|line| line.unwrap().parse::<u32>().unwrap());
+mod bulk;
+
+use bulk::BulkReadings;
+
+/// Slides a window of `window` readings over a series and reports how much
+/// the window sum changed with every new reading.
+///
+/// Two neighbouring windows share all but their first and last readings, so
+/// the change in sum is just a[i] - a[i - window]. Only the last `window`
+/// readings are kept around.
+#[derive(Debug)]
+struct SlidingWindow {
+    window: usize,
+    history: VecDeque<u32>,
+}
 
-    for item in data_iter {
-        window.push_back(item);
+impl SlidingWindow {
+    fn new(window: usize) -> Self {
+        assert!(window > 0, "Window size must be at least 1.");
+        SlidingWindow {
+            window,
+            history: VecDeque::with_capacity(window + 1),
+        }
+    }
 
-        if window.len() < 3 {
-            continue;
+    /// Returns `None` until there are two full windows to compare.
+    fn push(&mut self, item: u32) -> Option<i64> {
+        self.history.push_back(item);
+        if self.history.len() <= self.window {
+            return None;
         }
 
-        if last_measure.is_none() {
-            last_measure = Some(window.iter().sum::<u32>());
-            continue;
+        let oldest = self.history.pop_front().unwrap();
+        Some(item as i64 - oldest as i64)
+    }
+}
+
+/// Keeps a running count of windows whose sum is larger than the sum of the
+/// window before them.
+#[derive(Debug)]
+struct IncreaseCounter {
+    deltas: SlidingWindow,
+    count: usize,
+}
+
+impl IncreaseCounter {
+    fn new(window: usize) -> Self {
+        IncreaseCounter {
+            deltas: SlidingWindow::new(window),
+            count: 0,
         }
+    }
+
+    fn push(&mut self, item: u32) -> usize {
+        if let Some(delta) = self.deltas.push(item) {
+            if delta > 0 {
+                self.count += 1;
+            }
+        }
+
+        self.count
+    }
+}
+
+fn count_increases<I>(data: I, window: usize) -> usize
+where
+    I: IntoIterator<Item = u32>,
+{
+    let mut counter = IncreaseCounter::new(window);
+    for item in data {
+        counter.push(item);
+    }
+
+    counter.count
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrendConfig {
+    window: usize,
+    /// Changes with an absolute value above this count as large.
+    threshold: u64,
+    /// Width of each delta histogram bucket.
+    bucket: u64,
+}
+
+impl Default for TrendConfig {
+    fn default() -> Self {
+        TrendConfig {
+            window: 3,
+            threshold: 10,
+            bucket: 5,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrendReport {
+    increases: usize,
+    decreases: usize,
+    plateaus: usize,
+    large_changes: usize,
+    /// Most window increases in a row.
+    longest_increasing_run: usize,
+    largest_drop: Option<u64>,
+    /// Delta counts keyed by the lower bound of their bucket.
+    histogram: BTreeMap<i64, usize>,
+}
+
+fn trend_report<I>(data: I, config: &TrendConfig) -> TrendReport
+where
+    I: IntoIterator<Item = u32>,
+{
+    assert!(config.bucket > 0, "Histogram bucket width must be at least 1.");
+
+    let mut report = TrendReport::default();
+    let mut deltas = SlidingWindow::new(config.window);
+    let mut run = 0;
+    for item in data {
+        let delta = match deltas.push(item) {
+            Some(delta) => delta,
+            None => continue,
+        };
+
+        match delta.cmp(&0) {
+            Ordering::Greater => {
+                report.increases += 1;
+                run += 1;
+                report.longest_increasing_run = report.longest_increasing_run.max(run);
+            }
+            Ordering::Less => {
+                report.decreases += 1;
+                run = 0;
+                let drop = delta.unsigned_abs();
+                report.largest_drop = Some(report.largest_drop.map_or(drop, |d| d.max(drop)));
+            }
+            Ordering::Equal => {
+                report.plateaus += 1;
+                run = 0;
+            }
+        }
+
+        if delta.unsigned_abs() > config.threshold {
+            report.large_changes += 1;
+        }
+
+        let bucket = config.bucket as i64;
+        *report.histogram.entry(delta.div_euclid(bucket) * bucket).or_insert(0) += 1;
+    }
+
+    report
+}
+
+fn print_trend_report(report: &TrendReport, config: &TrendConfig) {
+    println!("Window size: {}", config.window);
+    println!("Increases: {}", report.increases);
+    println!("Decreases: {}", report.decreases);
+    println!("Plateaus: {}", report.plateaus);
+    println!("Changes larger than {}: {}", config.threshold, report.large_changes);
+    println!("Longest increasing run: {}", report.longest_increasing_run);
+    match report.largest_drop {
+        Some(drop) => println!("Largest drop: {}", drop),
+        None => println!("Largest drop: none"),
+    }
+    println!("Delta histogram:");
+    for (&lo, count) in report.histogram.iter() {
+        println!("  [{}, {}): {}", lo, lo + config.bucket as i64, count);
+    }
+}
+
+#[derive(Debug)]
+enum ReadingError {
+    Io { line: usize, err: io::Error },
+    Parse { line: usize, text: String, err: ParseIntError },
+    Malformed { line: usize, column: usize, reason: &'static str },
+}
+
+impl fmt::Display for ReadingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadingError::Io { line, err } => write!(f, "line {}: read failed: {}", line, err),
+            ReadingError::Parse { line, text, err } => {
+                write!(f, "line {}: bad reading {:?}: {}", line, text, err)
+            }
+            ReadingError::Malformed {
+                line,
+                column,
+                reason,
+            } => write!(f, "line {}, column {}: bad reading: {}", line, column, reason),
+        }
+    }
+}
+
+/// What to do with a line that isn't a valid reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strictness {
+    Skip,
+    Abort,
+}
+
+/// Parses readings line by line, numbering lines from 1. Only spaces, tabs
+/// and `\r` are trimmed, as in `BulkReadings`. Lines are split as bytes, so
+/// a corrupt line is a bad reading rather than a failed read.
+fn readings<R: BufRead>(reader: R) -> impl Iterator<Item = Result<u32, ReadingError>> {
+    reader.split(b'\n').enumerate().map(|(idx, line)| {
+        let line_no = idx + 1;
+        let line = line.map_err(|err| ReadingError::Io { line: line_no, err })?;
+        if let Some(pos) = line.iter().position(|b| !b.is_ascii()) {
+            return Err(ReadingError::Malformed {
+                line: line_no,
+                column: pos + 1,
+                reason: "non-ASCII byte",
+            });
+        }
+
+        let line = String::from_utf8(line).expect("ASCII is valid UTF-8");
+        line.trim_matches([' ', '\t', '\r'])
+            .parse::<u32>()
+            .map_err(|err| ReadingError::Parse {
+                line: line_no,
+                text: line,
+                err,
+            })
+    })
+}
+
+/// Reads readings from stdin and prints the running increase counts after
+/// every reading.
+fn stream(strictness: Strictness) -> Result<(), ReadingError> {
+    let mut single = IncreaseCounter::new(1);
+    let mut triple = IncreaseCounter::new(3);
+
+    let stdin = io::stdin();
+    for reading in readings(stdin.lock()) {
+        let item = match (reading, strictness) {
+            (Ok(item), _) => item,
+            (Err(err @ ReadingError::Io { .. }), _) | (Err(err), Strictness::Abort) => {
+                return Err(err)
+            }
+            (Err(err), Strictness::Skip) => {
+                eprintln!("Skipping {}", err);
+                continue;
+            }
+        };
+
+        println!(
+            "{}: increases {} (window 1), {} (window 3)",
+            item,
+            single.push(item),
+            triple.push(item)
+        );
+    }
+
+    Ok(())
+}
+
+/// Builds a newline separated random walk of `n` depth readings.
+fn synthetic_readings(n: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(n * 6);
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut depth: u32 = 10_000;
+    for _ in 0..n {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        depth = (depth + (state % 41) as u32).saturating_sub(18);
+        out.extend_from_slice(depth.to_string().as_bytes());
+        out.push(b'\n');
+    }
+
+    out
+}
+
+/// Times the `lines().map(parse)` path against `BulkReadings` on the same
+/// bytes, feeding both into the window logic.
+fn bench(input: &[u8]) {
+    type Ingest = fn(&[u8]) -> Result<(usize, usize), ReadingError>;
+    let paths: [(&str, Ingest); 2] = [
+        ("lines", |bytes| {
+            let data = readings(bytes).collect::<Result<Vec<_>, _>>()?;
+            Ok((data.len(), count_increases(data, 3)))
+        }),
+        ("bulk", |bytes| {
+            let data = BulkReadings::new(bytes).collect::<Result<Vec<_>, _>>()?;
+            Ok((data.len(), count_increases(data, 3)))
+        }),
+    ];
+
+    let megabytes = input.len() as f64 / (1024.0 * 1024.0);
+    println!("Input: {:.1} MiB", megabytes);
+    for (name, ingest) in paths {
+        let start = Instant::now();
+        let (n, increases) = ingest(input).unwrap_or_else(|err| {
+            eprintln!("{}: {}", name, err);
+            std::process::exit(1);
+        });
+        let secs = start.elapsed().as_secs_f64();
+        println!(
+            "{:>5}: {} readings, {} increases in {:.3}s ({:.1} MiB/s, {:.1}M readings/s)",
+            name,
+            n,
+            increases,
+            secs,
+            megabytes / secs,
+            n as f64 / secs / 1e6
+        );
+    }
+}
+
+/// Looks up the value following `flag` on the command line.
+fn flag_value<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+    let value = args.get(idx + 1).unwrap_or_else(|| {
+        eprintln!("{} needs a value.", flag);
+        std::process::exit(1);
+    });
+
+    Some(value.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid value for {}: {:?}", flag, value);
+        std::process::exit(1);
+    }))
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "stream") {
+        let strictness = if args.iter().any(|arg| arg == "--strict") {
+            Strictness::Abort
+        } else {
+            Strictness::Skip
+        };
+
+        if let Err(err) = stream(strictness) {
+            eprintln!("Aborting at {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "bench") {
+        let input = match flag_value::<String>(&args, "--file") {
+            Some(path) => std::fs::read(&path).expect("Can't open the file."),
+            None => synthetic_readings(flag_value(&args, "--readings").unwrap_or(10_000_000)),
+        };
+
+        bench(&input);
+        return;
+    }
+
+    let data = BulkReadings::new(File::open("aoc1.txt").expect("Can't open the file."))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|err| {
+            eprintln!("aoc1.txt: {}", err);
+            std::process::exit(1);
+        });
 
-        window.pop_front();
-        let new_measure = window.iter().sum::<u32>();
-        if new_measure > last_measure.unwrap() {
-            count += 1;
+    if args.iter().any(|arg| arg == "trend") {
+        let default = TrendConfig::default();
+        let config = TrendConfig {
+            window: flag_value(&args, "--window").unwrap_or(default.window),
+            threshold: flag_value(&args, "--threshold").unwrap_or(default.threshold),
+            bucket: flag_value(&args, "--bucket").unwrap_or(default.bucket),
+        };
+        if config.window == 0 || config.bucket == 0 {
+            eprintln!("--window and --bucket must be at least 1.");
+            std::process::exit(1);
         }
 
-        last_measure = Some(new_measure);
+        print_trend_report(&trend_report(data.iter().copied(), &config), &config);
+        return;
     }
 
-    println!("Total elevation increases: {}", count);
+    println!("Part 1: {}", count_increases(data.iter().copied(), 1));
+    println!("Part 2: {}", count_increases(data.iter().copied(), 3));
 }
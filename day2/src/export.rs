@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use crate::Navigate;
+
+const SVG_WIDTH: f64 = 1000.0;
+const SVG_HEIGHT: f64 = 400.0;
+const SVG_MARGIN: f64 = 20.0;
+
+/// Submarine state right after a command. Command 0 is the starting state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub command: usize,
+    pub horizontal: i64,
+    pub lateral: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct Trajectory(pub Vec<Sample>);
+
+impl Trajectory {
+    pub fn record<N: Navigate>(&mut self, command: usize, pos: &N) {
+        self.0.push(Sample {
+            command,
+            horizontal: pos.horizontal(),
+            lateral: pos.lateral(),
+            depth: pos.depth(),
+            aim: pos.aim(),
+        });
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("command,horizontal,lateral,depth,aim\n");
+        for s in self.0.iter() {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                s.command, s.horizontal, s.lateral, s.depth, s.aim
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    /// Draws the depth profile as a polyline, with depth growing downwards
+    /// and the surface marked when it is in view.
+    pub fn to_svg(&self) -> String {
+        let bounds = |f: fn(&Sample) -> i64| {
+            let min = self.0.iter().map(f).min().unwrap_or(0);
+            let max = self.0.iter().map(f).max().unwrap_or(0);
+            (min as f64, (max - min).max(1) as f64)
+        };
+        let (min_x, span_x) = bounds(|s| s.horizontal);
+        let (min_y, span_y) = bounds(|s| s.depth);
+        let scale_x =
+            |x: i64| SVG_MARGIN + (x as f64 - min_x) / span_x * (SVG_WIDTH - 2.0 * SVG_MARGIN);
+        let scale_y =
+            |y: i64| SVG_MARGIN + (y as f64 - min_y) / span_y * (SVG_HEIGHT - 2.0 * SVG_MARGIN);
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = SVG_WIDTH,
+            h = SVG_HEIGHT
+        )
+        .unwrap();
+        writeln!(out, r#"  <rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+        if min_y <= 0.0 && min_y + span_y >= 0.0 {
+            let y = scale_y(0);
+            writeln!(
+                out,
+                r#"  <line x1="0" y1="{y:.2}" x2="{w}" y2="{y:.2}" stroke="steelblue" stroke-dasharray="4"/>"#,
+                y = y,
+                w = SVG_WIDTH
+            )
+            .unwrap();
+        }
+
+        let points = self
+            .0
+            .iter()
+            .map(|s| format!("{:.2},{:.2}", scale_x(s.horizontal), scale_y(s.depth)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            r#"  <polyline points="{}" fill="none" stroke="black" stroke-width="1.5"/>"#,
+            points
+        )
+        .unwrap();
+        writeln!(out, "</svg>").unwrap();
+
+        out
+    }
+}
@@ -1,98 +1,567 @@
-use std::fs;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::str::FromStr;
+
+mod export;
+mod plan;
+mod script;
+mod seabed;
+
+use export::Trajectory;
+use seabed::{SeabedMap, Violation};
 
 #[derive(Default, Debug)]
 struct Position {
-    horizontal: u32,
-    depth: u32,
+    horizontal: i64,
+    depth: i64,
 }
 
 #[derive(Default, Debug)]
 struct AimedPosition {
-    horizontal: u32,
-    depth: u32,
-    aim: i32
+    horizontal: i64,
+    depth: i64,
+    aim: i64,
+}
+
+/// Compass heading in the horizontal plane, turned in quarter turns.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum Heading {
+    #[default]
+    East,
+    North,
+    West,
+    South,
+}
+
+impl Heading {
+    const ALL: [Heading; 4] = [Heading::East, Heading::North, Heading::West, Heading::South];
+
+    /// Turns counterclockwise by `quarter_turns`; negative turns go clockwise.
+    fn turn(self, quarter_turns: i64) -> Self {
+        let idx = (self as i64 + quarter_turns).rem_euclid(4);
+        Heading::ALL[idx as usize]
+    }
+
+    /// Unit step along (x, y) when moving forward.
+    fn step(self) -> (i64, i64) {
+        match self {
+            Heading::East => (1, 0),
+            Heading::North => (0, 1),
+            Heading::West => (-1, 0),
+            Heading::South => (0, -1),
+        }
+    }
 }
 
+/// Aimed navigation in 3D: `left` and `right` turn the heading by that many
+/// quarter turns, and `forward`/`back` move along it in the (x, y) plane.
+/// With no turns this matches `AimedPosition`, x being the horizontal
+/// position.
+#[derive(Default, Debug)]
+struct HeadingPosition {
+    x: i64,
+    y: i64,
+    depth: i64,
+    aim: i64,
+    heading: Heading,
+}
+
+/// A navigation model that a course can be run through.
+trait Navigate: Default + fmt::Debug {
+    /// Applies one movement, returning `None` and leaving the model untouched
+    /// if any coordinate would overflow.
+    fn apply(&mut self, movement: &Movement) -> Option<()>;
+
+    fn horizontal(&self) -> i64;
+
+    fn depth(&self) -> i64;
+
+    fn lateral(&self) -> i64 {
+        0
+    }
+
+    fn aim(&self) -> i64 {
+        0
+    }
+}
+
+impl Navigate for Position {
+    fn apply(&mut self, movement: &Movement) -> Option<()> {
+        let distance = movement.distance as i64;
+        match movement.direction {
+            Direction::Up => self.depth = self.depth.checked_sub(distance)?,
+            Direction::Down => self.depth = self.depth.checked_add(distance)?,
+            Direction::Forward => self.horizontal = self.horizontal.checked_add(distance)?,
+            Direction::Back => self.horizontal = self.horizontal.checked_sub(distance)?,
+            Direction::Left | Direction::Right => {}
+        }
+
+        Some(())
+    }
+
+    fn horizontal(&self) -> i64 {
+        self.horizontal
+    }
+
+    fn depth(&self) -> i64 {
+        self.depth
+    }
+}
+
+impl Navigate for AimedPosition {
+    fn apply(&mut self, movement: &Movement) -> Option<()> {
+        let distance = movement.distance as i64;
+        match movement.direction {
+            Direction::Up => self.aim = self.aim.checked_sub(distance)?,
+            Direction::Down => self.aim = self.aim.checked_add(distance)?,
+            Direction::Forward => {
+                let horizontal = self.horizontal.checked_add(distance)?;
+                let depth = self.depth.checked_add(distance.checked_mul(self.aim)?)?;
+                self.horizontal = horizontal;
+                self.depth = depth;
+            }
+            Direction::Back => {
+                let horizontal = self.horizontal.checked_sub(distance)?;
+                let depth = self.depth.checked_sub(distance.checked_mul(self.aim)?)?;
+                self.horizontal = horizontal;
+                self.depth = depth;
+            }
+            Direction::Left | Direction::Right => {}
+        }
+
+        Some(())
+    }
+
+    fn horizontal(&self) -> i64 {
+        self.horizontal
+    }
+
+    fn depth(&self) -> i64 {
+        self.depth
+    }
+
+    fn aim(&self) -> i64 {
+        self.aim
+    }
+}
+
+impl Navigate for HeadingPosition {
+    fn apply(&mut self, movement: &Movement) -> Option<()> {
+        let distance = movement.distance as i64;
+        let forward = match movement.direction {
+            Direction::Up => {
+                self.aim = self.aim.checked_sub(distance)?;
+                return Some(());
+            }
+            Direction::Down => {
+                self.aim = self.aim.checked_add(distance)?;
+                return Some(());
+            }
+            Direction::Left => {
+                self.heading = self.heading.turn(distance % 4);
+                return Some(());
+            }
+            Direction::Right => {
+                self.heading = self.heading.turn(-(distance % 4));
+                return Some(());
+            }
+            Direction::Forward => true,
+            Direction::Back => false,
+        };
+        // Subtracting for `back` rather than negating keeps `i64::MIN` terms
+        // from overflowing unnoticed.
+        let advance = |from: i64, by: i64| {
+            if forward {
+                from.checked_add(by)
+            } else {
+                from.checked_sub(by)
+            }
+        };
+
+        let (dx, dy) = self.heading.step();
+        let x = advance(self.x, distance.checked_mul(dx)?)?;
+        let y = advance(self.y, distance.checked_mul(dy)?)?;
+        let depth = advance(self.depth, distance.checked_mul(self.aim)?)?;
+        self.x = x;
+        self.y = y;
+        self.depth = depth;
+
+        Some(())
+    }
+
+    fn horizontal(&self) -> i64 {
+        self.x
+    }
+
+    fn lateral(&self) -> i64 {
+        self.y
+    }
+
+    fn depth(&self) -> i64 {
+        self.depth
+    }
+
+    fn aim(&self) -> i64 {
+        self.aim
+    }
+}
+
+/// The navigation models a course can be run through, picked at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Model {
+    Position,
+    Aimed,
+    Heading,
+}
+
+impl Model {
+    const ALL: [Model; 3] = [Model::Position, Model::Aimed, Model::Heading];
+
+    fn name(self) -> &'static str {
+        match self {
+            Model::Position => "position",
+            Model::Aimed => "aimed",
+            Model::Heading => "heading",
+        }
+    }
+
+    fn run(self, course: &[Movement]) -> Run {
+        match self {
+            Model::Position => run::<Position>(course),
+            Model::Aimed => run::<AimedPosition>(course),
+            Model::Heading => run::<HeadingPosition>(course),
+        }
+    }
+
+    fn validate(self, course: &[Movement], map: &SeabedMap) -> Result<(), Violation> {
+        match self {
+            Model::Position => seabed::validate::<Position>(course, map),
+            Model::Aimed => seabed::validate::<AimedPosition>(course, map),
+            Model::Heading => seabed::validate::<HeadingPosition>(course, map),
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Model::ALL
+            .into_iter()
+            .find(|model| model.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown model {:?}, expected \"position\", \"aimed\" or \"heading\".",
+                    s
+                )
+            })
+    }
+}
+
+/// Something notable that happened while running a course. Commands are
+/// numbered from 1, matching the course file lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Surfaced(usize),
+    Overflow(usize),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Surfaced(n) => write!(f, "surfaced at command {}", n),
+            Event::Overflow(n) => write!(f, "overflow at command {}", n),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Run {
+    /// Debug print of the model's final state.
+    pos: String,
+    events: Vec<Event>,
+    trajectory: Trajectory,
+}
+
+/// Runs the course through a navigation model, recording the state after
+/// every movement. The run stops at the first overflow, leaving the model in
+/// its last valid state.
+fn run<N: Navigate>(course: &[Movement]) -> Run {
+    let mut pos = N::default();
+    let mut events = vec![];
+    let mut trajectory = Trajectory::default();
+    trajectory.record(0, &pos);
+    for (idx, movement) in course.iter().enumerate() {
+        let was_submerged = pos.depth() >= 0;
+        if pos.apply(movement).is_none() {
+            events.push(Event::Overflow(idx + 1));
+            break;
+        }
+
+        trajectory.record(idx + 1, &pos);
+        if was_submerged && pos.depth() < 0 {
+            events.push(Event::Surfaced(idx + 1));
+        }
+    }
+
+    Run {
+        pos: format!("{:?}", pos),
+        events,
+        trajectory,
+    }
+}
+
+fn report(name: &str, run: &Run) {
+    for event in run.events.iter() {
+        println!("{}: {}", name, event);
+    }
+
+    let last = run.trajectory.0.last().unwrap();
+    match last.horizontal.checked_mul(last.depth) {
+        Some(product) => println!(
+            "{}: Final position: {}. Product: {}",
+            name, run.pos, product
+        ),
+        None => println!("{}: Final position: {}. Product overflows", name, run.pos),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
     Forward,
+    Back,
+    Left,
+    Right,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Movement {
     direction: Direction,
     distance: u32,
 }
 
-fn part1(fname: &str) {
-    let f = fs::File::open(fname).expect("Unable to open input.");
-    let data_iter = BufReader::new(f).lines().map(|line| {
-        let line = line.unwrap();
-        let mut parts_iter = line.split_whitespace();
-        let direction = match parts_iter.next().unwrap() {
-            "forward" => Direction::Forward,
-            "down" => Direction::Down,
-            "up" => Direction::Up,
-            _ => panic!("Uknown direction."),
+#[derive(Debug, PartialEq, Eq)]
+enum MovementError {
+    Empty,
+    UnknownDirection(String),
+    MissingDistance,
+    InvalidDistance(String),
+    /// Anything left on the line after the distance.
+    TrailingInput(String),
+}
+
+impl fmt::Display for MovementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovementError::Empty => write!(f, "empty line"),
+            MovementError::UnknownDirection(s) => write!(f, "unknown direction {:?}", s),
+            MovementError::MissingDistance => write!(f, "missing distance"),
+            MovementError::InvalidDistance(s) => write!(f, "invalid distance {:?}", s),
+            MovementError::TrailingInput(s) => write!(f, "unexpected {:?} after distance", s),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Forward => "forward",
+            Direction::Back => "back",
+            Direction::Left => "left",
+            Direction::Right => "right",
         };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.direction, self.distance)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = MovementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(Direction::Forward),
+            "down" => Ok(Direction::Down),
+            "up" => Ok(Direction::Up),
+            "back" => Ok(Direction::Back),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(MovementError::UnknownDirection(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for Movement {
+    type Err = MovementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts_iter = s.split_whitespace();
+        let direction = parts_iter
+            .next()
+            .ok_or(MovementError::Empty)?
+            .parse::<Direction>()?;
 
-        let distance = parts_iter.next().unwrap().parse::<u32>().unwrap();
+        let distance = parts_iter.next().ok_or(MovementError::MissingDistance)?;
+        let distance = distance
+            .parse::<u32>()
+            .map_err(|_| MovementError::InvalidDistance(distance.to_string()))?;
+
+        let rest = parts_iter.collect::<Vec<_>>();
+        if !rest.is_empty() {
+            return Err(MovementError::TrailingInput(rest.join(" ")));
+        }
 
-        Movement {
+        Ok(Movement {
             direction,
             distance,
-        }
+        })
+    }
+}
+
+/// Parses one movement per line, reporting the first bad line (1-based).
+fn parse_course(input: &str) -> Result<Vec<Movement>, (usize, MovementError)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| line.parse::<Movement>().map_err(|err| (idx + 1, err)))
+        .collect()
+}
+
+/// Prints a planned course in the plain course format, then checks on
+/// stderr where it actually ends up.
+fn print_plan(args: &[String], model: Model) {
+    let target = args
+        .iter()
+        .position(|arg| arg == "--target")
+        .and_then(|idx| args.get(idx + 1))
+        .and_then(|s| s.split_once(','))
+        .and_then(|(h, d)| Some((h.trim().parse::<i64>().ok()?, d.trim().parse::<i64>().ok()?)));
+    let (horizontal, depth) = target.unwrap_or_else(|| {
+        eprintln!("plan needs --target HORIZONTAL,DEPTH.");
+        std::process::exit(1);
     });
+    let max_commands = args
+        .iter()
+        .position(|arg| arg == "--max-commands")
+        .map(|idx| {
+            args.get(idx + 1)
+                .and_then(|s| s.parse::<usize>().ok())
+                .expect("--max-commands needs a number.")
+        });
 
-    let mut pos = Position::default();
-    for movement in data_iter {
-        match movement.direction {
-            Direction::Up => pos.depth -= movement.distance,
-            Direction::Down => pos.depth += movement.distance,
-            Direction::Forward => pos.horizontal += movement.distance,
-        }
+    let course = plan::plan(model, horizontal, depth, max_commands).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    for movement in course.iter() {
+        println!("{}", movement);
     }
 
-    println!("Final position: {:?}. Product: {}", pos, pos.horizontal * pos.depth);
+    let run = model.run(&course);
+    for event in run.events.iter() {
+        eprintln!("{}", event);
+    }
+    let last = *run.trajectory.0.last().unwrap();
+    eprintln!(
+        "{} commands, ending at horizontal {}, depth {}",
+        course.len(),
+        last.horizontal,
+        last.depth
+    );
 }
 
-fn part2(fname: &str) {
-    let f = fs::File::open(fname).expect("Unable to open input.");
-    let data_iter = BufReader::new(f).lines().map(|line| {
-        let line = line.unwrap();
-        let mut parts_iter = line.split_whitespace();
-        let direction = match parts_iter.next().unwrap() {
-            "forward" => Direction::Forward,
-            "down" => Direction::Down,
-            "up" => Direction::Up,
-            _ => panic!("Uknown direction."),
-        };
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let selected = args.iter().position(|arg| arg == "--model").map(|idx| {
+        let name = args.get(idx + 1).map_or("", String::as_str);
+        name.parse::<Model>().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+    });
 
-        let distance = parts_iter.next().unwrap().parse::<u32>().unwrap();
+    if args.iter().any(|arg| arg == "plan") {
+        print_plan(&args, selected.unwrap_or(Model::Aimed));
+        return;
+    }
 
-        Movement {
-            direction,
-            distance,
+    let course = match args.iter().position(|arg| arg == "script") {
+        Some(idx) => {
+            let fname = args.get(idx + 1).expect("script needs a file name.");
+            let src = std::fs::read_to_string(fname).expect("Unable to open script.");
+            script::expand(&src).unwrap_or_else(|err| {
+                eprintln!("{}: {}", fname, err);
+                std::process::exit(1);
+            })
         }
-    });
+        None => {
+            let input = std::fs::read_to_string("aoc2.txt").expect("Unable to open input.");
+            parse_course(&input).unwrap_or_else(|(line, err)| {
+                eprintln!("aoc2.txt:{}: {}", line, err);
+                std::process::exit(1);
+            })
+        }
+    };
 
-    let mut pos = AimedPosition::default();
-    for movement in data_iter {
-        match movement.direction {
-            Direction::Up => pos.aim -= movement.distance as i32,
-            Direction::Down => pos.aim += movement.distance as i32,
-            Direction::Forward => {
-                pos.horizontal += movement.distance;
-                pos.depth += (movement.distance as i32 * pos.aim) as u32;
+    let models = match selected {
+        Some(model) => vec![model],
+        None => Model::ALL.to_vec(),
+    };
+    let runs = models
+        .into_iter()
+        .map(|model| (model, model.run(&course)))
+        .collect::<Vec<_>>();
+    for (model, run) in runs.iter() {
+        report(model.name(), run);
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--seabed") {
+        let fname = args.get(idx + 1).expect("--seabed needs a file name.");
+        let scale = args
+            .iter()
+            .position(|arg| arg == "--scale")
+            .map_or(1, |idx| {
+                args.get(idx + 1)
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .expect("--scale needs a number.")
+            });
+        let input = std::fs::read_to_string(fname).expect("Unable to open seabed map.");
+        let map = SeabedMap::parse(&input, scale).unwrap_or_else(|err| {
+            eprintln!("{}: {}", fname, err);
+            std::process::exit(1);
+        });
+
+        for (model, _) in runs.iter() {
+            match model.validate(&course, &map) {
+                Ok(()) => println!("{}: course stays clear of the seabed", model.name()),
+                Err(violation) => println!("{}: course {}", model.name(), violation),
             }
         }
     }
 
-    println!("Final position: {:?}. Product: {}", pos, pos.horizontal * pos.depth);
-}
-
-fn main() {
-    part1("aoc2part1.txt");
-    part2("aoc2part2.txt");
+    let exported = selected.unwrap_or(Model::Aimed);
+    let trajectory = &runs
+        .iter()
+        .find(|(model, _)| *model == exported)
+        .unwrap()
+        .1
+        .trajectory;
+    for flag in ["--csv", "--svg"] {
+        if let Some(idx) = args.iter().position(|arg| arg == flag) {
+            let fname = args.get(idx + 1).expect("Export needs a file name.");
+            let contents = match flag {
+                "--csv" => trajectory.to_csv(),
+                _ => trajectory.to_svg(),
+            };
+            std::fs::write(fname, contents).expect("Unable to write export.");
+        }
+    }
 }
@@ -0,0 +1,243 @@
+//! Builds a short course that ends at a given position.
+//!
+//! A plan is first laid out as legs, each a total amount of movement in one
+//! direction. Legs longer than a `u32` are split across repeated commands,
+//! so the length of a plan is known before any command is built.
+
+use std::fmt;
+
+use crate::{Direction, Model, Movement};
+
+/// The longest distance a single command can carry.
+const MAX_DISTANCE: u128 = u32::MAX as u128;
+
+/// Most commands a plan may have, whatever `--max-commands` asks for.
+const MAX_COMMANDS: usize = 10_000_000;
+
+/// How many aims are tried when looking for one that divides the depth.
+const DIVISOR_SEARCH: u128 = 1 << 16;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    UnsupportedModel(Model),
+    TooManyCommands { needed: usize, max: usize },
+    Unreachable { horizontal: i64, depth: i64 },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::UnsupportedModel(model) => {
+                write!(f, "can't plan courses for the {} model", model.name())
+            }
+            PlanError::TooManyCommands { needed, max } => write!(
+                f,
+                "reaching the target takes {} commands, more than the maximum of {}",
+                needed, max
+            ),
+            PlanError::Unreachable { horizontal, depth } => write!(
+                f,
+                "no course reaches horizontal {}, depth {} without overflowing",
+                horizontal, depth
+            ),
+        }
+    }
+}
+
+/// Movement in one direction, before it is split into commands.
+#[derive(Debug, Clone, Copy)]
+struct Leg {
+    direction: Direction,
+    amount: u128,
+}
+
+impl Leg {
+    fn signed(amount: i128, pos: Direction, neg: Direction) -> Leg {
+        Leg {
+            direction: if amount < 0 { neg } else { pos },
+            amount: amount.unsigned_abs(),
+        }
+    }
+
+    fn horizontal(amount: i128) -> Leg {
+        Leg::signed(amount, Direction::Forward, Direction::Back)
+    }
+
+    fn vertical(amount: i128) -> Leg {
+        Leg::signed(amount, Direction::Down, Direction::Up)
+    }
+}
+
+/// Number of commands `legs` split into.
+fn commands(legs: &[Leg]) -> u128 {
+    legs.iter()
+        .map(|leg| leg.amount.div_ceil(MAX_DISTANCE))
+        .sum()
+}
+
+/// Where `legs` end in `model`, or `None` if the model overflows on the way.
+/// The commands of one leg all move the same way, so it is enough to check
+/// where each leg ends and the largest single step within it.
+fn end(model: Model, legs: &[Leg]) -> Option<(i64, i64)> {
+    let fits = |value: i128| i64::try_from(value).is_ok();
+    let (mut horizontal, mut depth, mut aim) = (0i128, 0i128, 0i128);
+    for leg in legs {
+        let amount = leg.amount as i128;
+        let (across, down) = match leg.direction {
+            Direction::Forward => (amount, 0),
+            Direction::Back => (-amount, 0),
+            Direction::Down => (0, amount),
+            Direction::Up => (0, -amount),
+            Direction::Left | Direction::Right => (0, 0),
+        };
+        horizontal += across;
+        if model == Model::Aimed {
+            aim += down;
+            let step = aim.checked_mul(across.signum() * amount.min(MAX_DISTANCE as i128))?;
+            depth = depth.checked_add(aim.checked_mul(across)?)?;
+            if !fits(aim) || !fits(step) {
+                return None;
+            }
+        } else {
+            depth += down;
+        }
+        if !fits(horizontal) || !fits(depth) {
+            return None;
+        }
+    }
+
+    Some((horizontal as i64, depth as i64))
+}
+
+/// In the aimed model depth only changes while moving horizontally, by the
+/// distance times the current aim (negated when moving back). Small targets
+/// are reached in at most three commands:
+///
+/// - no depth: move straight there;
+/// - depth divisible by the horizontal position: aim once, then move;
+/// - otherwise: move all but one step level, aim, and take the last step;
+/// - no horizontal position: step forward, aim, and step back.
+fn three_step(horizontal: i128, depth: i128) -> Vec<Leg> {
+    if depth == 0 {
+        vec![Leg::horizontal(horizontal)]
+    } else if horizontal == 0 {
+        vec![
+            Leg::horizontal(1),
+            Leg::vertical(-depth),
+            Leg::horizontal(-1),
+        ]
+    } else if depth % horizontal == 0 {
+        vec![
+            Leg::vertical(depth / horizontal),
+            Leg::horizontal(horizontal),
+        ]
+    } else {
+        let step = horizontal.signum();
+        vec![
+            Leg::horizontal(horizontal - step),
+            Leg::vertical(depth * step),
+            Leg::horizontal(step),
+        ]
+    }
+}
+
+/// Aims, makes all the depth in one run of at most a `u32`, levels off and
+/// moves the rest of the way. Needs an aim that divides the depth, looked
+/// for among the smallest that keep the run short enough.
+fn factored(horizontal: i128, depth: i128, dir: i128) -> Option<Vec<Leg>> {
+    let size = depth.unsigned_abs();
+    let lowest = size.div_ceil(MAX_DISTANCE).max(1);
+    let aim = (lowest..lowest + DIVISOR_SEARCH)
+        .take_while(|&aim| aim <= MAX_DISTANCE)
+        .find(|&aim| size.is_multiple_of(aim))?;
+    let run = dir * (size / aim) as i128;
+    let aim = depth / run;
+    Some(vec![
+        Leg::vertical(aim),
+        Leg::horizontal(run),
+        Leg::vertical(-aim),
+        Leg::horizontal(horizontal - run),
+    ])
+}
+
+/// Makes the depth as a quotient and remainder of the longest distance:
+/// aims by the quotient for one full run, then by one for the remainder,
+/// levels off and moves the rest of the way. Works for any depth.
+fn split(horizontal: i128, depth: i128, dir: i128) -> Vec<Leg> {
+    let size = depth.unsigned_abs();
+    let (quotient, remainder) = ((size / MAX_DISTANCE) as i128, (size % MAX_DISTANCE) as i128);
+    let sign = depth.signum() * dir;
+    let mut legs = vec![];
+    let (mut aim, mut run) = (0, 0);
+    if quotient > 0 {
+        legs.push(Leg::vertical(sign * quotient));
+        legs.push(Leg::horizontal(dir * MAX_DISTANCE as i128));
+        aim = sign * quotient;
+        run += dir * MAX_DISTANCE as i128;
+    }
+    if remainder > 0 {
+        legs.push(Leg::vertical(sign - aim));
+        legs.push(Leg::horizontal(dir * remainder));
+        aim = sign;
+        run += dir * remainder;
+    }
+    legs.push(Leg::vertical(-aim));
+    legs.push(Leg::horizontal(horizontal - run));
+    legs
+}
+
+fn aimed_candidates(horizontal: i128, depth: i128) -> Vec<Vec<Leg>> {
+    let mut candidates = vec![three_step(horizontal, depth)];
+    if depth != 0 {
+        for dir in [1, -1] {
+            candidates.extend(factored(horizontal, depth, dir));
+            candidates.push(split(horizontal, depth, dir));
+        }
+    }
+    candidates
+}
+
+/// Plans a course that ends at (`horizontal`, `depth`) in `model`.
+///
+/// Of the constructions tried, the one with the fewest commands that never
+/// overflows the model wins. Its length is checked against `max_commands`,
+/// and a fixed limit, before any command is built.
+pub fn plan(
+    model: Model,
+    horizontal: i64,
+    depth: i64,
+    max_commands: Option<usize>,
+) -> Result<Vec<Movement>, PlanError> {
+    let (h, d) = (horizontal as i128, depth as i128);
+    let candidates = match model {
+        Model::Position => vec![vec![Leg::horizontal(h), Leg::vertical(d)]],
+        Model::Aimed => aimed_candidates(h, d),
+        Model::Heading => return Err(PlanError::UnsupportedModel(model)),
+    };
+    let legs = candidates
+        .into_iter()
+        .filter(|legs| end(model, legs) == Some((horizontal, depth)))
+        .min_by_key(|legs| commands(legs))
+        .ok_or(PlanError::Unreachable { horizontal, depth })?;
+
+    let needed = usize::try_from(commands(&legs)).unwrap_or(usize::MAX);
+    let max = max_commands.map_or(MAX_COMMANDS, |max| max.min(MAX_COMMANDS));
+    if needed > max {
+        return Err(PlanError::TooManyCommands { needed, max });
+    }
+
+    let mut course = Vec::with_capacity(needed);
+    for leg in legs {
+        let mut left = leg.amount;
+        while left > 0 {
+            let distance = left.min(MAX_DISTANCE) as u32;
+            course.push(Movement {
+                direction: leg.direction,
+                distance,
+            });
+            left -= distance as u128;
+        }
+    }
+
+    Ok(course)
+}
@@ -0,0 +1,399 @@
+//! A small scripting layer over plain course files.
+//!
+//! ```text
+//! # comments run to the end of the line
+//! let step = 5
+//! macro zigzag {
+//!     down $step
+//!     forward 2
+//!     up $step
+//! }
+//! repeat 3 {
+//!     zigzag
+//!     back 1
+//! }
+//! ```
+//!
+//! A script expands into the same `Movement` stream a plain course parses to,
+//! so every plain course is also a valid script.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Direction, Movement};
+
+/// Macros calling macros deeper than this are assumed to recurse forever.
+const MAX_CALL_DEPTH: usize = 64;
+/// Upper bound on the number of movements a script may expand into.
+const MAX_MOVEMENTS: usize = 10_000_000;
+/// Upper bound on repeat iterations plus macro calls, so loops that emit no
+/// movements still end.
+const MAX_STEPS: usize = 10_000_000;
+
+/// 1-based line and column in the script source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pos {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    pub pos: Pos,
+    pub msg: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.pos.line, self.pos.col, self.msg)
+    }
+}
+
+fn error<T>(pos: Pos, msg: impl Into<String>) -> Result<T, ScriptError> {
+    Err(ScriptError {
+        pos,
+        msg: msg.into(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Var(String),
+    Number(u32),
+    LBrace,
+    RBrace,
+    Eq,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "{:?}", s),
+            Token::Var(s) => write!(f, "\"${}\"", s),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::LBrace => write!(f, "\"{{\""),
+            Token::RBrace => write!(f, "\"}}\""),
+            Token::Eq => write!(f, "\"=\""),
+        }
+    }
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Pos, Token)>, ScriptError> {
+    let mut tokens = vec![];
+    for (line_idx, line) in src.lines().enumerate() {
+        let chars = line.char_indices().collect::<Vec<_>>();
+        let mut idx = 0;
+        while idx < chars.len() {
+            let (start, ch) = chars[idx];
+            let pos = Pos {
+                line: line_idx + 1,
+                col: idx + 1,
+            };
+
+            // Skips over identifier characters, returning the end byte offset.
+            let scan_word = |from: usize, idx: &mut usize| {
+                let mut end = from;
+                while end < chars.len() && is_ident_char(chars[end].1) {
+                    end += 1;
+                }
+                *idx = end;
+                chars.get(end).map_or(line.len(), |&(offset, _)| offset)
+            };
+
+            let token = match ch {
+                '#' => break,
+                _ if ch.is_whitespace() => {
+                    idx += 1;
+                    continue;
+                }
+                '{' => {
+                    idx += 1;
+                    Token::LBrace
+                }
+                '}' => {
+                    idx += 1;
+                    Token::RBrace
+                }
+                '=' => {
+                    idx += 1;
+                    Token::Eq
+                }
+                '$' => {
+                    let end = scan_word(idx + 1, &mut idx);
+                    let name = &line[start + 1..end];
+                    if name.is_empty() {
+                        return error(pos, "expected a variable name after \"$\"");
+                    }
+                    Token::Var(name.to_string())
+                }
+                _ if ch.is_ascii_digit() => {
+                    let end = scan_word(idx, &mut idx);
+                    let word = &line[start..end];
+                    match word.parse::<u32>() {
+                        Ok(n) => Token::Number(n),
+                        Err(_) => return error(pos, format!("invalid number {:?}", word)),
+                    }
+                }
+                _ if is_ident_char(ch) => {
+                    let end = scan_word(idx, &mut idx);
+                    Token::Ident(line[start..end].to_string())
+                }
+                _ => return error(pos, format!("unexpected character {:?}", ch)),
+            };
+
+            tokens.push((pos, token));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Literal(u32),
+    Var(Pos, String),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Move(Pos, Direction, Value),
+    Repeat(Pos, Value, Vec<Stmt>),
+    Macro(String, Vec<Stmt>),
+    Let(String, Value),
+    Call(Pos, String),
+}
+
+struct Parser {
+    tokens: Vec<(Pos, Token)>,
+    idx: usize,
+    eof: Pos,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Pos, Token)> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<(Pos, Token), ScriptError> {
+        match self.tokens.get(self.idx) {
+            Some(item) => {
+                self.idx += 1;
+                Ok(item.clone())
+            }
+            None => error(
+                self.eof,
+                format!("expected {}, found end of script", expected),
+            ),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ScriptError> {
+        let (pos, found) = self.next(&token.to_string())?;
+        if found != token {
+            return error(pos, format!("expected {}, found {}", token, found));
+        }
+
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, ScriptError> {
+        match self.next("a name")? {
+            (_, Token::Ident(name)) if !is_keyword(&name) => Ok(name),
+            (pos, found) => error(pos, format!("expected a name, found {}", found)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ScriptError> {
+        match self.next("a number or variable")? {
+            (_, Token::Number(n)) => Ok(Value::Literal(n)),
+            (pos, Token::Var(name)) => Ok(Value::Var(pos, name)),
+            (pos, found) => error(
+                pos,
+                format!("expected a number or variable, found {}", found),
+            ),
+        }
+    }
+
+    /// Parses statements up to the end of the script, or up to the closing
+    /// brace of a block when `in_block` is set.
+    fn block(&mut self, in_block: bool) -> Result<Vec<Stmt>, ScriptError> {
+        let mut stmts = vec![];
+        loop {
+            let (pos, token) = match self.peek() {
+                None if in_block => return error(self.eof, "expected \"}\", found end of script"),
+                None => return Ok(stmts),
+                Some(item) => item.clone(),
+            };
+            self.idx += 1;
+
+            let stmt = match token {
+                Token::RBrace if in_block => return Ok(stmts),
+                Token::Ident(word) => match word.as_str() {
+                    "repeat" => {
+                        let count = self.value()?;
+                        self.expect(Token::LBrace)?;
+                        Stmt::Repeat(pos, count, self.block(true)?)
+                    }
+                    "macro" => {
+                        let name = self.name()?;
+                        self.expect(Token::LBrace)?;
+                        Stmt::Macro(name, self.block(true)?)
+                    }
+                    "let" => {
+                        let name = self.name()?;
+                        self.expect(Token::Eq)?;
+                        Stmt::Let(name, self.value()?)
+                    }
+                    _ => match word.parse::<Direction>() {
+                        Ok(direction) => Stmt::Move(pos, direction, self.value()?),
+                        Err(_) => Stmt::Call(pos, word),
+                    },
+                },
+                found => return error(pos, format!("expected a statement, found {}", found)),
+            };
+
+            stmts.push(stmt);
+        }
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(word, "repeat" | "macro" | "let") || word.parse::<Direction>().is_ok()
+}
+
+#[derive(Default)]
+struct Expander {
+    vars: HashMap<String, u32>,
+    macros: HashMap<String, Vec<Stmt>>,
+    out: Vec<Movement>,
+    steps: usize,
+}
+
+impl Expander {
+    fn eval(&self, value: &Value) -> Result<u32, ScriptError> {
+        match value {
+            Value::Literal(n) => Ok(*n),
+            Value::Var(pos, name) => match self.vars.get(name) {
+                Some(&n) => Ok(n),
+                None => error(*pos, format!("undefined variable \"${}\"", name)),
+            },
+        }
+    }
+
+    /// Movements `stmts` would expand into with the variables and macros
+    /// defined so far, saturating. Names that aren't defined yet count as
+    /// nothing, `run` reports them. Each macro is only walked once.
+    fn moves_in(&self, stmts: &[Stmt], macro_moves: &mut HashMap<String, usize>) -> usize {
+        stmts.iter().fold(0, |acc: usize, stmt| {
+            let moves = match stmt {
+                Stmt::Move(..) => 1,
+                Stmt::Repeat(_, count, body) => {
+                    let count = self.eval(count).unwrap_or(0) as usize;
+                    count.saturating_mul(self.moves_in(body, macro_moves))
+                }
+                Stmt::Call(_, name) => match macro_moves.get(name) {
+                    Some(&moves) => moves,
+                    None => {
+                        // Recursive calls see 0 and get caught by the depth limit.
+                        macro_moves.insert(name.clone(), 0);
+                        let moves = self
+                            .macros
+                            .get(name)
+                            .map_or(0, |body| self.moves_in(body, macro_moves));
+                        macro_moves.insert(name.clone(), moves);
+                        moves
+                    }
+                },
+                Stmt::Macro(..) | Stmt::Let(..) => 0,
+            };
+            acc.saturating_add(moves)
+        })
+    }
+
+    fn step(&mut self, pos: Pos) -> Result<(), ScriptError> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return error(pos, "script runs too many repeats or macro calls");
+        }
+
+        Ok(())
+    }
+
+    fn run(&mut self, stmts: &[Stmt], depth: usize, at: Pos) -> Result<(), ScriptError> {
+        if depth > MAX_CALL_DEPTH {
+            return error(at, "macro calls nested too deep");
+        }
+
+        for stmt in stmts {
+            match stmt {
+                Stmt::Move(pos, direction, value) => {
+                    if self.out.len() == MAX_MOVEMENTS {
+                        return error(*pos, "script expands into too many movements");
+                    }
+                    let distance = self.eval(value)?;
+                    self.out.push(Movement {
+                        direction: *direction,
+                        distance,
+                    });
+                }
+                Stmt::Repeat(pos, count, body) => {
+                    let count = self.eval(count)?;
+                    if body.is_empty() {
+                        continue;
+                    }
+
+                    let moves =
+                        (count as usize).saturating_mul(self.moves_in(body, &mut HashMap::new()));
+                    if self.out.len().saturating_add(moves) > MAX_MOVEMENTS {
+                        return error(*pos, "script expands into too many movements");
+                    }
+
+                    for _ in 0..count {
+                        self.step(*pos)?;
+                        self.run(body, depth, at)?;
+                    }
+                }
+                Stmt::Macro(name, body) => {
+                    self.macros.insert(name.clone(), body.clone());
+                }
+                Stmt::Let(name, value) => {
+                    let n = self.eval(value)?;
+                    self.vars.insert(name.clone(), n);
+                }
+                Stmt::Call(pos, name) => {
+                    let body = match self.macros.get(name) {
+                        Some(body) => body.clone(),
+                        None => return error(*pos, format!("undefined macro {:?}", name)),
+                    };
+                    self.step(*pos)?;
+                    self.run(&body, depth + 1, *pos)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses and expands a script into the movements it describes.
+pub fn expand(src: &str) -> Result<Vec<Movement>, ScriptError> {
+    let eof = Pos {
+        line: src.lines().count().max(1),
+        col: src.lines().last().map_or(0, |l| l.chars().count()) + 1,
+    };
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        idx: 0,
+        eof,
+    };
+    let stmts = parser.block(false)?;
+
+    let mut expander = Expander::default();
+    expander.run(&stmts, 0, Pos { line: 1, col: 1 })?;
+    Ok(expander.out)
+}
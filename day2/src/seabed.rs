@@ -0,0 +1,184 @@
+//! Checks a course against a seabed depth map.
+//!
+//! The map uses the digit grid format from day 9: row `y`, column `x` holds
+//! the seabed depth under that spot, in multiples of a scale factor.
+
+use std::fmt;
+
+use crate::{Direction, Movement, Navigate};
+
+pub struct SeabedMap {
+    grid: Vec<Vec<u8>>,
+    scale: i64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MapError {
+    Empty,
+    /// Scales below 1 would put the seabed at or above the surface.
+    BadScale(i64),
+    BadDigit {
+        line: usize,
+        col: usize,
+        ch: char,
+    },
+    RaggedRow {
+        line: usize,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "map is empty"),
+            MapError::BadScale(scale) => write!(f, "scale must be at least 1, got {}", scale),
+            MapError::BadDigit { line, col, ch } => {
+                write!(f, "{}:{}: expected a digit, found {:?}", line, col, ch)
+            }
+            MapError::RaggedRow {
+                line,
+                len,
+                expected,
+            } => write!(f, "{}: row has {} cells, expected {}", line, len, expected),
+        }
+    }
+}
+
+impl SeabedMap {
+    pub fn parse(input: &str, scale: i64) -> Result<Self, MapError> {
+        if scale < 1 {
+            return Err(MapError::BadScale(scale));
+        }
+
+        let mut grid: Vec<Vec<u8>> = vec![];
+        for (line_idx, line) in input.trim().lines().enumerate() {
+            let row = line
+                .trim()
+                .chars()
+                .enumerate()
+                .map(|(col_idx, ch)| match ch.to_digit(10) {
+                    Some(d) => Ok(d as u8),
+                    None => Err(MapError::BadDigit {
+                        line: line_idx + 1,
+                        col: col_idx + 1,
+                        ch,
+                    }),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if let Some(first) = grid.first() {
+                if row.len() != first.len() {
+                    return Err(MapError::RaggedRow {
+                        line: line_idx + 1,
+                        len: row.len(),
+                        expected: first.len(),
+                    });
+                }
+            }
+            grid.push(row);
+        }
+
+        if grid.is_empty() || grid[0].is_empty() {
+            return Err(MapError::Empty);
+        }
+
+        Ok(SeabedMap { grid, scale })
+    }
+
+    /// Seabed depth at (x, y), or `None` off the map.
+    fn depth_at(&self, x: i64, y: i64) -> Option<i64> {
+        let row = self.grid.get(usize::try_from(y).ok()?)?;
+        let cell = row.get(usize::try_from(x).ok()?)?;
+        Some(*cell as i64 * self.scale)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    HitSeabed,
+    LeftMap,
+    Overflow,
+}
+
+/// Where a course first goes wrong. Command 0 is the starting position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub command: usize,
+    pub horizontal: i64,
+    pub lateral: i64,
+    pub depth: i64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ViolationKind::HitSeabed => "hits the seabed",
+            ViolationKind::LeftMap => "leaves the map",
+            ViolationKind::Overflow => "overflows",
+        };
+        write!(
+            f,
+            "{} at command {} (horizontal {}, lateral {}, depth {})",
+            what, self.command, self.horizontal, self.lateral, self.depth
+        )
+    }
+}
+
+fn check<N: Navigate>(map: &SeabedMap, command: usize, pos: &N) -> Result<(), Violation> {
+    let violation = |kind| Violation {
+        kind,
+        command,
+        horizontal: pos.horizontal(),
+        lateral: pos.lateral(),
+        depth: pos.depth(),
+    };
+
+    match map.depth_at(pos.horizontal(), pos.lateral()) {
+        None => Err(violation(ViolationKind::LeftMap)),
+        Some(seabed) if pos.depth() >= seabed => Err(violation(ViolationKind::HitSeabed)),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Runs the course through a navigation model and returns the first point
+/// where it hits the seabed or leaves the map.
+///
+/// `forward` and `back` are checked one step at a time so passing through a
+/// ridge is caught. Every other command moves at most one coordinate
+/// monotonically, so checking where it ends is enough.
+pub fn validate<N: Navigate>(course: &[Movement], map: &SeabedMap) -> Result<(), Violation> {
+    let mut pos = N::default();
+    check(map, 0, &pos)?;
+
+    for (idx, movement) in course.iter().enumerate() {
+        let command = idx + 1;
+        let (steps, step) = match movement.direction {
+            Direction::Forward | Direction::Back => (
+                movement.distance,
+                Movement {
+                    direction: movement.direction,
+                    distance: 1,
+                },
+            ),
+            _ => (1, *movement),
+        };
+
+        for _ in 0..steps {
+            if pos.apply(&step).is_none() {
+                return Err(Violation {
+                    kind: ViolationKind::Overflow,
+                    command,
+                    horizontal: pos.horizontal(),
+                    lateral: pos.lateral(),
+                    depth: pos.depth(),
+                });
+            }
+            check(map, command, &pos)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,126 +1,387 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::fs;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
-fn most_common_bits<T>(data: &Vec<T>) -> Vec<usize>
-where
-    T: AsRef<str>,
-{
-    let initial = vec![0; data[0].as_ref().len()];
-    let counts = data.iter().fold(initial, |mut acc, s| {
-        let s = s.as_ref();
-        for (ch, count) in s.chars().zip(acc.iter_mut()) {
-            if ch == '1' {
-                *count += 1;
+mod symbols;
+mod trie;
+
+use symbols::SymbolReport;
+use trie::{Criteria, Trie};
+
+/// Product of two `u128`s, as little-endian 64-bit limbs. Ratings of reports
+/// wider than 64 bits multiply past `u128`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn mul(a: u128, b: u128) -> U256 {
+        let a = [a as u64, (a >> 64) as u64];
+        let b = [b as u64, (b >> 64) as u64];
+        let mut limbs = [0u64; 4];
+        for (i, &x) in a.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &y) in b.iter().enumerate() {
+                let t = x as u128 * y as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = t as u64;
+                carry = t >> 64;
             }
+            limbs[i + 2] = carry as u64;
         }
 
-        acc
-    });
+        U256(limbs)
+    }
+}
 
-    let cutoff = data.len() / 2;
-    counts
-        .iter()
-        .map(|&count| {
-            if count > cutoff {
-                1
-            } else if data.len() - count == count {
-                1
-            } else {
-                0
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Peel off 19 decimal digits at a time, most significant limb first.
+        const CHUNK: u128 = 10_000_000_000_000_000_000;
+        let mut limbs = self.0;
+        let mut chunks = vec![];
+        loop {
+            let mut rem = 0u128;
+            for limb in limbs.iter_mut().rev() {
+                let cur = (rem << 64) | *limb as u128;
+                *limb = (cur / CHUNK) as u64;
+                rem = cur % CHUNK;
             }
-        })
-        .collect()
+            chunks.push(rem as u64);
+
+            if limbs.iter().all(|&l| l == 0) {
+                break;
+            }
+        }
+
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:019}", chunk)?;
+        }
+
+        Ok(())
+    }
 }
 
-fn least_common_bits<T>(data: &Vec<T>) -> Vec<usize>
-where
-    T: AsRef<str>,
-{
-    most_common_bits(data)
-        .iter()
-        .map(|&n| if n == 1 { 0 } else { 1 })
-        .collect()
+/// A diagnostic report with every line packed into one word. Column 0 is the
+/// leftmost, most significant bit.
+#[derive(Debug, Clone)]
+struct Report {
+    words: Vec<u128>,
+    width: usize,
 }
 
-fn part1(data: &Vec<String>) -> u32 {
-    let gamma = most_common_bits(data)
-        .iter()
-        .map(|&count| if count == 1 { '1' } else { '0' })
-        .collect::<String>();
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    Empty,
+    TooWide {
+        width: usize,
+    },
+    BadChar {
+        line: usize,
+        col: usize,
+        ch: char,
+    },
+    NotInAlphabet {
+        line: usize,
+        col: usize,
+        ch: char,
+    },
+    WrongLength {
+        line: usize,
+        len: usize,
+        expected: usize,
+    },
+}
 
-    let epsilon = gamma
-        .chars()
-        .map(|ch| if ch == '1' { '0' } else { '1' })
-        .collect::<String>();
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "report is empty"),
+            ParseError::TooWide { width } => {
+                write!(
+                    f,
+                    "lines are {} bits wide, at most 128 are supported",
+                    width
+                )
+            }
+            ParseError::BadChar { line, col, ch } => {
+                write!(
+                    f,
+                    "line {}, column {}: expected 0 or 1, found {:?}",
+                    line, col, ch
+                )
+            }
+            ParseError::NotInAlphabet { line, col, ch } => write!(
+                f,
+                "line {}, column {}: {:?} is not in the alphabet",
+                line, col, ch
+            ),
+            ParseError::WrongLength {
+                line,
+                len,
+                expected,
+            } => write!(f, "line {}: {} bits long, expected {}", line, len, expected),
+        }
+    }
+}
 
-    let gamma = u32::from_str_radix(&gamma, 2).unwrap();
-    let epsilon = u32::from_str_radix(&epsilon, 2).unwrap();
+impl Report {
+    /// Parses one binary number per line. Lines and columns in errors are
+    /// numbered from 1, and the first line sets the width.
+    fn parse<T>(data: &[T]) -> Result<Report, ParseError>
+    where
+        T: AsRef<str>,
+    {
+        let width = data
+            .first()
+            .ok_or(ParseError::Empty)?
+            .as_ref()
+            .chars()
+            .count();
+        if width == 0 {
+            return Err(ParseError::Empty);
+        }
+        if width > 128 {
+            return Err(ParseError::TooWide { width });
+        }
 
-    gamma * epsilon
-}
+        let mut words = Vec::with_capacity(data.len());
+        for (line_idx, s) in data.iter().enumerate() {
+            let mut word = 0u128;
+            let mut len = 0;
+            for (col_idx, ch) in s.as_ref().chars().enumerate() {
+                let bit = match ch {
+                    '0' => 0,
+                    '1' => 1,
+                    _ => {
+                        return Err(ParseError::BadChar {
+                            line: line_idx + 1,
+                            col: col_idx + 1,
+                            ch,
+                        })
+                    }
+                };
+                if col_idx < width {
+                    word = (word << 1) | bit;
+                }
+                len += 1;
+            }
 
-fn part2(data: &Vec<String>) -> u32 {
-    let data: Vec<&String> = data.iter().map(|item| item).collect();
-    let max_len = data[0].len();
+            if len != width {
+                return Err(ParseError::WrongLength {
+                    line: line_idx + 1,
+                    len,
+                    expected: width,
+                });
+            }
+            words.push(word);
+        }
 
-    let oxygen: u32 = {
-        let mut search_space = data.clone();
-        for idx in 0..max_len {
-            let criteria_bit = most_common_bits(&search_space)[idx];
-            search_space = search_space
-                .into_iter()
-                .filter(|&s| {
-                    let idx_bit = s.as_bytes()[idx] as u8 - 48;
-                    idx_bit == criteria_bit as u8
-                })
-                .collect::<Vec<_>>();
+        Ok(Report { words, width })
+    }
 
-            if search_space.len() == 1 {
-                break;
+    fn mask(&self) -> u128 {
+        match self.width {
+            128 => u128::MAX,
+            w => (1 << w) - 1,
+        }
+    }
+
+    fn bit(&self, word: u128, col: usize) -> u128 {
+        (word >> (self.width - 1 - col)) & 1
+    }
+
+    /// Number of ones in every column.
+    fn column_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.width];
+        for &word in self.words.iter() {
+            let mut rest = word;
+            while rest != 0 {
+                let shift = rest.trailing_zeros() as usize;
+                counts[self.width - 1 - shift] += 1;
+                rest &= rest - 1;
             }
         }
 
-        if search_space.len() != 1 {
-            panic!("More than one number meeting the criteria.")
+        counts
+    }
+}
+
+/// How to settle a column with as many zeros as ones. The policy names the
+/// bit the most common criterion keeps; the least common one keeps the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TiePolicy {
+    PreferOne,
+    PreferZero,
+    Error,
+}
+
+impl TiePolicy {
+    fn most_common(self, zeros: usize, ones: usize, column: usize) -> Result<u8, RatingError> {
+        match (ones.cmp(&zeros), self) {
+            (Ordering::Greater, _) | (Ordering::Equal, TiePolicy::PreferOne) => Ok(1),
+            (Ordering::Less, _) | (Ordering::Equal, TiePolicy::PreferZero) => Ok(0),
+            (Ordering::Equal, TiePolicy::Error) => Err(RatingError::Tie { column }),
         }
+    }
+}
 
-        u32::from_str_radix(search_space[0], 2).unwrap()
-    };
+impl FromStr for TiePolicy {
+    type Err = String;
 
-    let co2: u32 = {
-        let mut search_space = data.clone();
-        for idx in 0..max_len {
-            let criteria_bit = least_common_bits(&search_space)[idx];
-            search_space = search_space
-                .into_iter()
-                .filter(|&s| {
-                    let idx_bit = s.as_bytes()[idx] as u8 - 48;
-                    idx_bit == criteria_bit as u8
-                })
-                .collect::<Vec<_>>();
-
-            if search_space.len() == 1 {
-                break;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "one" => Ok(TiePolicy::PreferOne),
+            "zero" => Ok(TiePolicy::PreferZero),
+            "error" => Ok(TiePolicy::Error),
+            _ => Err(format!(
+                "Unknown tie policy {:?}, expected \"one\", \"zero\" or \"error\".",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RatingError {
+    /// A column was split evenly under `TiePolicy::Error`.
+    Tie { column: usize },
+    /// The criterion kept a bit no remaining line has.
+    NoCandidates { column: usize },
+    /// Several identical lines were left after the last column.
+    Ambiguous { remaining: usize },
+}
+
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatingError::Tie { column } => write!(f, "tie in column {}", column),
+            RatingError::NoCandidates { column } => {
+                write!(f, "no number meets the criteria at column {}", column)
+            }
+            RatingError::Ambiguous { remaining } => {
+                write!(f, "{} numbers meet the criteria", remaining)
             }
         }
+    }
+}
+
+/// Most common bit of every column, packed like a report line.
+fn most_common_bits(report: &Report, ties: TiePolicy) -> Result<u128, RatingError> {
+    let len = report.words.len();
+    report
+        .column_counts()
+        .iter()
+        .enumerate()
+        .try_fold(0, |acc, (column, &ones)| {
+            let bit = ties.most_common(len - ones, ones, column)?;
+            Ok((acc << 1) | bit as u128)
+        })
+}
+
+fn least_common_bits(report: &Report, ties: TiePolicy) -> Result<u128, RatingError> {
+    Ok(!most_common_bits(report, ties)? & report.mask())
+}
 
-        if search_space.len() != 1 {
-            panic!("More than one number meeting the criteria.")
+fn part1(report: &Report, ties: TiePolicy) -> Result<U256, RatingError> {
+    let gamma = most_common_bits(report, ties)?;
+    let epsilon = least_common_bits(report, ties)?;
+
+    Ok(U256::mul(gamma, epsilon))
+}
+
+fn part2(report: &Report, ties: TiePolicy, explain: bool) -> Result<U256, RatingError> {
+    let trie = Trie::build(report);
+    let rating = |name: &str, criteria: Criteria| {
+        let mut trace = vec![];
+        let result = trie.rating(criteria, ties, &mut trace);
+        if explain {
+            println!("{} rating ({:?}):", name, criteria);
+            for round in trace.iter() {
+                println!("  {}", round);
+            }
         }
 
-        u32::from_str_radix(search_space[0], 2).unwrap()
+        result
     };
 
-    oxygen * co2
+    let oxygen = rating("Oxygen", Criteria::MostCommon)?;
+    let co2 = rating("CO2", Criteria::LeastCommon)?;
+
+    Ok(U256::mul(oxygen, co2))
+}
+
+fn print_answer(part: usize, answer: Result<U256, RatingError>) {
+    match answer {
+        Ok(n) => println!("Part {}: {}", part, n),
+        Err(err) => println!("Part {}: {}", part, err),
+    }
+}
+
+/// Runs both parts over a report of arbitrary symbols, printing each
+/// rating as a symbol string.
+fn symbol_parts(data: &[String], alphabet: Option<&str>, ties: TiePolicy, count_absent: bool) {
+    let report = SymbolReport::parse(data, alphabet)
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+        .count_absent(count_absent);
+
+    let part1 = symbols::most_common_symbols(&report, ties).and_then(|gamma| {
+        let epsilon = symbols::least_common_symbols(&report, ties)?;
+        Ok(format!("gamma {}, epsilon {}", gamma, epsilon))
+    });
+    let part2 = symbols::rating(&report, Criteria::MostCommon, ties).and_then(|oxygen| {
+        let co2 = symbols::rating(&report, Criteria::LeastCommon, ties)?;
+        Ok(format!("oxygen {}, co2 {}", oxygen, co2))
+    });
+
+    for (part, answer) in [(1, part1), (2, part2)] {
+        match answer {
+            Ok(s) => println!("Part {}: {}", part, s),
+            Err(err) => println!("Part {}: {}", part, err),
+        }
+    }
 }
 
 fn main() {
-    let f = fs::File::open("aoc3.txt").expect("Unable to open input.");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| args.get(idx + 1).map_or("", String::as_str))
+    };
+
+    let fname = flag_value("--input").unwrap_or("aoc3.txt");
+    let f = fs::File::open(fname).expect("Unable to open input.");
     let data = BufReader::new(f)
         .lines()
         .map(|line| line.unwrap())
         .collect::<Vec<_>>();
 
-    println!("Part 1: {}", part1(&data));
-    println!("Part 2: {}", part2(&data));
+    let ties = match flag_value("--ties") {
+        Some(policy) => policy.parse::<TiePolicy>().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => TiePolicy::PreferOne,
+    };
+    let explain = args.iter().any(|arg| arg == "--explain");
+
+    let alphabet = flag_value("--alphabet");
+    if alphabet.is_some() || args.iter().any(|arg| arg == "--symbols") {
+        let count_absent = args.iter().any(|arg| arg == "--count-absent");
+        symbol_parts(&data, alphabet, ties, count_absent);
+        return;
+    }
+
+    let report = Report::parse(&data).unwrap_or_else(|err| {
+        eprintln!("{}: {}", fname, err);
+        std::process::exit(1);
+    });
+
+    print_answer(1, part1(&report, ties));
+    print_answer(2, part2(&report, ties, explain));
 }
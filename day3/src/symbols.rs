@@ -0,0 +1,189 @@
+//! Column majorities over any alphabet, for reports that aren't binary.
+//!
+//! Symbols are ordered as given in the alphabet, or in code point order when
+//! it is inferred. Tie policies carry over from the binary report: with
+//! `PreferOne` the most common criterion settles ties on the last tied
+//! symbol and the least common one on the first, which is exactly what the
+//! binary rules do for the alphabet `01`.
+//!
+//! Only symbols that actually occur among the lines being looked at compete,
+//! so a column made of a single symbol has it as both its most and least
+//! common symbol. With `count_absent` every alphabet symbol competes instead,
+//! with a count of 0 where it doesn't occur, just as a column of all ones
+//! still has zero as its least common bit. A rating that then keeps an absent
+//! symbol fails with `NoCandidates`, like the binary one.
+
+use std::collections::BTreeSet;
+
+use crate::trie::Criteria;
+use crate::{ParseError, RatingError, TiePolicy};
+
+#[derive(Debug, Clone)]
+pub struct SymbolReport {
+    alphabet: Vec<char>,
+    /// Every line as indices into `alphabet`.
+    lines: Vec<Vec<usize>>,
+    width: usize,
+    count_absent: bool,
+}
+
+impl SymbolReport {
+    /// Parses one word per line. Without an explicit alphabet every symbol
+    /// in the report is allowed.
+    pub fn parse<T>(data: &[T], alphabet: Option<&str>) -> Result<SymbolReport, ParseError>
+    where
+        T: AsRef<str>,
+    {
+        let alphabet = match alphabet {
+            Some(symbols) => symbols.chars().collect::<Vec<_>>(),
+            None => data
+                .iter()
+                .flat_map(|s| s.as_ref().chars())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+
+        let width = data
+            .first()
+            .ok_or(ParseError::Empty)?
+            .as_ref()
+            .chars()
+            .count();
+        if width == 0 {
+            return Err(ParseError::Empty);
+        }
+
+        let mut lines = Vec::with_capacity(data.len());
+        for (line_idx, s) in data.iter().enumerate() {
+            let line = s
+                .as_ref()
+                .chars()
+                .enumerate()
+                .map(|(col_idx, ch)| {
+                    alphabet
+                        .iter()
+                        .position(|&a| a == ch)
+                        .ok_or(ParseError::NotInAlphabet {
+                            line: line_idx + 1,
+                            col: col_idx + 1,
+                            ch,
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if line.len() != width {
+                return Err(ParseError::WrongLength {
+                    line: line_idx + 1,
+                    len: line.len(),
+                    expected: width,
+                });
+            }
+            lines.push(line);
+        }
+
+        Ok(SymbolReport {
+            alphabet,
+            lines,
+            width,
+            count_absent: false,
+        })
+    }
+
+    /// Lets symbols missing from a column compete with a count of 0, as the
+    /// binary rules do for the alphabet `01`.
+    pub fn count_absent(mut self, count_absent: bool) -> Self {
+        self.count_absent = count_absent;
+        self
+    }
+
+    fn word(&self, symbols: &[usize]) -> String {
+        symbols.iter().map(|&idx| self.alphabet[idx]).collect()
+    }
+}
+
+/// Index of the symbol `criteria` picks in `column` among `lines`.
+fn pick(
+    report: &SymbolReport,
+    lines: &[&[usize]],
+    column: usize,
+    criteria: Criteria,
+    ties: TiePolicy,
+) -> Result<usize, RatingError> {
+    let mut counts = vec![0; report.alphabet.len()];
+    for line in lines {
+        counts[line[column]] += 1;
+    }
+
+    let competing = || (0..counts.len()).filter(|&idx| report.count_absent || counts[idx] > 0);
+    let target = match criteria {
+        Criteria::MostCommon => competing().map(|idx| counts[idx]).max(),
+        Criteria::LeastCommon => competing().map(|idx| counts[idx]).min(),
+    }
+    .ok_or(RatingError::NoCandidates { column })?;
+
+    let tied = competing()
+        .filter(|&idx| counts[idx] == target)
+        .collect::<Vec<_>>();
+    let prefer_last = match (ties, criteria) {
+        _ if tied.len() == 1 => true,
+        (TiePolicy::Error, _) => return Err(RatingError::Tie { column }),
+        (TiePolicy::PreferOne, Criteria::MostCommon) => true,
+        (TiePolicy::PreferOne, Criteria::LeastCommon) => false,
+        (TiePolicy::PreferZero, Criteria::MostCommon) => false,
+        (TiePolicy::PreferZero, Criteria::LeastCommon) => true,
+    };
+
+    Ok(if prefer_last {
+        tied[tied.len() - 1]
+    } else {
+        tied[0]
+    })
+}
+
+fn common_symbols(
+    report: &SymbolReport,
+    criteria: Criteria,
+    ties: TiePolicy,
+) -> Result<String, RatingError> {
+    let lines = report.lines.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let symbols = (0..report.width)
+        .map(|column| pick(report, &lines, column, criteria, ties))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(report.word(&symbols))
+}
+
+pub fn most_common_symbols(report: &SymbolReport, ties: TiePolicy) -> Result<String, RatingError> {
+    common_symbols(report, Criteria::MostCommon, ties)
+}
+
+pub fn least_common_symbols(report: &SymbolReport, ties: TiePolicy) -> Result<String, RatingError> {
+    common_symbols(report, Criteria::LeastCommon, ties)
+}
+
+/// Filters lines column by column, keeping those with the symbol `criteria`
+/// picks, until one line is left.
+pub fn rating(
+    report: &SymbolReport,
+    criteria: Criteria,
+    ties: TiePolicy,
+) -> Result<String, RatingError> {
+    let mut search_space = report.lines.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    for column in 0..report.width {
+        if search_space.len() == 1 {
+            break;
+        }
+
+        let symbol = pick(report, &search_space, column, criteria, ties)?;
+        search_space.retain(|line| line[column] == symbol);
+        if search_space.is_empty() {
+            return Err(RatingError::NoCandidates { column });
+        }
+    }
+
+    match search_space.len() {
+        1 => Ok(report.word(search_space[0])),
+        remaining => Err(RatingError::Ambiguous { remaining }),
+    }
+}
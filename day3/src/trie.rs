@@ -0,0 +1,143 @@
+//! Binary trie over report lines, for the life support rating filters.
+
+use std::fmt;
+
+use crate::{RatingError, Report, TiePolicy};
+
+#[derive(Debug, Default, Clone)]
+struct Node {
+    /// Lines passing through this node.
+    count: usize,
+    children: [Option<usize>; 2],
+}
+
+/// Which bit a rating keeps at each column, given the counts of zeros and
+/// ones among the lines still in the running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criteria {
+    MostCommon,
+    LeastCommon,
+}
+
+impl Criteria {
+    fn pick(
+        self,
+        counts: [usize; 2],
+        ties: TiePolicy,
+        column: usize,
+    ) -> Result<usize, RatingError> {
+        let most = ties.most_common(counts[0], counts[1], column)? as usize;
+        match self {
+            Criteria::MostCommon => Ok(most),
+            Criteria::LeastCommon => Ok(1 - most),
+        }
+    }
+}
+
+/// One filtering round of a rating walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Round {
+    pub column: usize,
+    pub zeros: usize,
+    pub ones: usize,
+    pub kept: usize,
+    pub remaining: usize,
+}
+
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "column {}: {} zeros, {} ones, keep {}{}, {} left",
+            self.column,
+            self.zeros,
+            self.ones,
+            self.kept,
+            if self.zeros == self.ones {
+                " (tie)"
+            } else {
+                ""
+            },
+            self.remaining
+        )
+    }
+}
+
+/// Nodes live in one arena, root first. Depth `d` branches on column `d`.
+#[derive(Debug)]
+pub struct Trie {
+    nodes: Vec<Node>,
+    width: usize,
+}
+
+impl Trie {
+    pub fn build(report: &Report) -> Trie {
+        let mut nodes = vec![Node::default()];
+        for &word in report.words.iter() {
+            let mut node = 0;
+            nodes[node].count += 1;
+            for col in 0..report.width {
+                let bit = report.bit(word, col) as usize;
+                node = match nodes[node].children[bit] {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(Node::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children[bit] = Some(child);
+                        child
+                    }
+                };
+                nodes[node].count += 1;
+            }
+        }
+
+        Trie {
+            nodes,
+            width: report.width,
+        }
+    }
+
+    fn count(&self, child: Option<usize>) -> usize {
+        child.map_or(0, |idx| self.nodes[idx].count)
+    }
+
+    /// Walks down from the root, keeping the bit `criteria` picks at every
+    /// column and recording each filtering round in `trace`. Once a single
+    /// line is left the walk just follows it.
+    pub fn rating(
+        &self,
+        criteria: Criteria,
+        ties: TiePolicy,
+        trace: &mut Vec<Round>,
+    ) -> Result<u128, RatingError> {
+        let mut node = &self.nodes[0];
+        let mut value = 0;
+        for column in 0..self.width {
+            let counts = node.children.map(|child| self.count(child));
+            let bit = if node.count == 1 {
+                (counts[1] == 1) as usize
+            } else {
+                let bit = criteria.pick(counts, ties, column)?;
+                trace.push(Round {
+                    column,
+                    zeros: counts[0],
+                    ones: counts[1],
+                    kept: bit,
+                    remaining: counts[bit],
+                });
+                bit
+            };
+
+            node = match node.children[bit] {
+                Some(child) => &self.nodes[child],
+                None => return Err(RatingError::NoCandidates { column }),
+            };
+            value = (value << 1) | bit as u128;
+        }
+
+        match node.count {
+            1 => Ok(value),
+            remaining => Err(RatingError::Ambiguous { remaining }),
+        }
+    }
+}
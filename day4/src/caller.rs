@@ -0,0 +1,112 @@
+//! A live caller: reads draws and commands one per line and announces
+//! winners as they happen.
+//!
+//! Commands are a number to draw it, `show N` to print board `N`, `near` to
+//! list boards one number away from winning, `undo` to take back the last
+//! draw and `quit`.
+
+use std::io::BufRead;
+
+use crate::Game;
+
+struct Draw {
+    n: u32,
+    /// Whether `n` was new. Repeats mark nothing and have nothing to undo.
+    fresh: bool,
+    winners: Vec<usize>,
+}
+
+enum Command {
+    Draw(u32),
+    Show(usize),
+    Near,
+    Undo,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["show", id] => id
+            .parse()
+            .map(Command::Show)
+            .map_err(|_| format!("Invalid board {:?}.", id)),
+        ["near"] => Ok(Command::Near),
+        ["undo"] => Ok(Command::Undo),
+        ["quit"] => Ok(Command::Quit),
+        [n] => n
+            .parse()
+            .map(Command::Draw)
+            .map_err(|_| format!("Unknown command {:?}.", line.trim())),
+        _ => Err(format!("Unknown command {:?}.", line.trim())),
+    }
+}
+
+pub fn run(mut game: Game, input: impl BufRead) {
+    let mut history: Vec<Draw> = vec![];
+    let mut won = 0;
+
+    for line in input.lines() {
+        let line = line.expect("Unable to read input.");
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match parse_command(&line) {
+            Ok(command) => command,
+            Err(err) => {
+                println!("{}", err);
+                continue;
+            }
+        };
+
+        match command {
+            Command::Draw(n) => {
+                let fresh = !history.iter().any(|d| d.n == n);
+                let mut winners = vec![];
+                for (id, pattern) in game.draw(n) {
+                    won += 1;
+                    println!(
+                        "Board {} wins by {} with score {} (winner #{})",
+                        id,
+                        game.patterns[pattern],
+                        game.boards[id].unmarked_sum() * n,
+                        won
+                    );
+                    winners.push(id);
+                }
+                if winners.is_empty() {
+                    println!("Drew {}, no winner", n);
+                }
+                history.push(Draw { n, fresh, winners });
+            }
+            Command::Show(id) => match game.boards.get(id) {
+                Some(board) => print!("{}", board),
+                None => println!("No board {}.", id),
+            },
+            Command::Near => {
+                let mut any = false;
+                for (id, board) in game.boards.iter().enumerate() {
+                    for (n, pattern) in board.near_wins() {
+                        println!("Board {} needs {} for {}", id, n, game.patterns[pattern]);
+                        any = true;
+                    }
+                }
+                if !any {
+                    println!("No board is one number away.");
+                }
+            }
+            Command::Undo => match history.pop() {
+                Some(draw) => {
+                    if draw.fresh {
+                        game.undraw(draw.n);
+                    }
+                    won -= draw.winners.len();
+                    println!("Took back {}", draw.n);
+                }
+                None => println!("Nothing to undo."),
+            },
+            Command::Quit => break,
+        }
+    }
+}
@@ -1,120 +1,482 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 
+mod caller;
+mod montecarlo;
+mod pattern;
+
+use pattern::Pattern;
+
 #[derive(Debug, Clone, Copy)]
 struct MarkableNum {
     n: u32,
     marked: bool,
 }
 
+/// A set of cells that wins the board once all of them are marked.
+#[derive(Debug, Clone)]
+struct WinLine {
+    /// Index into the game's active patterns.
+    pattern: usize,
+    /// Cells as `row * cols + col`.
+    cells: Vec<usize>,
+    marks: usize,
+}
+
+/// A bingo board of any size. Every row has the same length.
+///
+/// Marked counts per win line, the unmarked sum and the win state are kept
+/// up to date as cells get marked, so none of them need a rescan.
 #[derive(Debug, Clone)]
 struct Board {
     grid: Vec<Vec<MarkableNum>>,
+    lines: Vec<WinLine>,
+    /// Win lines going through each cell, indexed by `row * cols + col`.
+    cell_lines: Vec<Vec<usize>>,
+    unmarked_sum: u32,
+    won: bool,
 }
 
 impl Board {
-    fn mark(&mut self, n: u32) {
-        for row in self.grid.iter_mut() {
-            for elem in row.iter_mut() {
-                if (*elem).n == n {
-                    (*elem).marked = true;
+    fn new(grid: Vec<Vec<MarkableNum>>) -> Self {
+        let unmarked_sum = grid.iter().flatten().map(|mn| mn.n).sum();
+        let mut board = Board {
+            grid,
+            lines: vec![],
+            cell_lines: vec![],
+            unmarked_sum,
+            won: false,
+        };
+        board.set_patterns(&[Pattern::Line]);
+        board
+    }
+
+    fn rows(&self) -> usize {
+        self.grid.len()
+    }
+
+    fn cols(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    /// Replaces the win lines with those of `patterns`. Earlier patterns take
+    /// precedence when one mark completes several.
+    fn set_patterns(&mut self, patterns: &[Pattern]) {
+        let (rows, cols) = (self.rows(), self.cols());
+        self.lines.clear();
+        self.cell_lines = vec![vec![]; rows * cols];
+        for (p_idx, pattern) in patterns.iter().enumerate() {
+            for cells in pattern.masks(rows, cols) {
+                let line_idx = self.lines.len();
+                let mut marks = 0;
+                for &cell in cells.iter() {
+                    self.cell_lines[cell].push(line_idx);
+                    marks += self.grid[cell / cols][cell % cols].marked as usize;
                 }
+                self.lines.push(WinLine {
+                    pattern: p_idx,
+                    cells,
+                    marks,
+                });
             }
         }
     }
 
-    fn is_winner(&self) -> bool {
-        let rows = (0..5).map(|row_idx| (0..5).map(move |col_idx| self.grid[row_idx][col_idx]));
-        let cols = (0..5).map(|col_idx| (0..5).map(move |row_idx| self.grid[row_idx][col_idx]));
-        for (mut row_line, mut col_line) in rows.zip(cols) {
-            if row_line.all(|n| n.marked) || col_line.all(|n| n.marked) {
-                return true;
+    /// Marks one cell. Returns the pattern that won the board if this mark is
+    /// what made it a winner.
+    fn mark_cell(&mut self, row: usize, col: usize) -> Option<usize> {
+        let cell = &mut self.grid[row][col];
+        if cell.marked {
+            return None;
+        }
+
+        cell.marked = true;
+        self.unmarked_sum -= cell.n;
+
+        let mut completed: Option<usize> = None;
+        for &line_idx in self.cell_lines[row * self.cols() + col].iter() {
+            let line = &mut self.lines[line_idx];
+            line.marks += 1;
+            if line.marks == line.cells.len() {
+                completed = Some(completed.map_or(line.pattern, |p| p.min(line.pattern)));
             }
         }
 
-        false
+        if completed.is_some() && !self.won {
+            self.won = true;
+            return completed;
+        }
+
+        None
+    }
+
+    /// Takes back a mark. The board stops being a winner once none of its
+    /// lines is complete.
+    fn unmark_cell(&mut self, row: usize, col: usize) {
+        let cell = &mut self.grid[row][col];
+        if !cell.marked {
+            return;
+        }
+
+        cell.marked = false;
+        self.unmarked_sum += cell.n;
+
+        for &line_idx in self.cell_lines[row * self.cols() + col].iter() {
+            self.lines[line_idx].marks -= 1;
+        }
+        self.won = self.lines.iter().any(|l| l.marks == l.cells.len());
+    }
+
+    /// Numbers that would complete a line, each with the earliest pattern it
+    /// completes. Empty once the board has won.
+    fn near_wins(&self) -> Vec<(u32, usize)> {
+        if self.won {
+            return vec![];
+        }
+
+        let cols = self.cols();
+        let mut near: Vec<(u32, usize)> = vec![];
+        for line in self.lines.iter().filter(|l| l.marks + 1 == l.cells.len()) {
+            let missing = line
+                .cells
+                .iter()
+                .map(|&cell| self.grid[cell / cols][cell % cols])
+                .find(|mn| !mn.marked)
+                .unwrap();
+            match near.iter_mut().find(|(n, _)| *n == missing.n) {
+                Some((_, pattern)) => *pattern = (*pattern).min(line.pattern),
+                None => near.push((missing.n, line.pattern)),
+            }
+        }
+
+        near.sort_unstable();
+        near
     }
 
     fn unmarked_sum(&self) -> u32 {
-        self.grid
-            .iter()
-            .map(|row| row.iter())
-            .flatten()
-            .filter_map(|mn| (!mn.marked).then(|| mn.n))
-            .sum()
-    }
-}
-
-fn part1(input: &str) {
-    let (nums, mut boards) = parse_input(input);
-    for n in nums {
-        for b in boards.iter_mut() {
-            b.mark(n);
-            if b.is_winner() {
-                println!("Part 1: {}", b.unmarked_sum() * n);
-                return;
+        self.unmarked_sum
+    }
+}
+
+/// Cell position as (board, row, col).
+type CellPos = (usize, usize, usize);
+
+/// All boards in a game, plus where every number sits on them, so a draw
+/// only touches the cells holding that number.
+#[derive(Debug, Clone)]
+struct Game {
+    boards: Vec<Board>,
+    patterns: Vec<Pattern>,
+    index: HashMap<u32, Vec<CellPos>>,
+}
+
+impl Game {
+    fn new(mut boards: Vec<Board>, patterns: Vec<Pattern>) -> Self {
+        let mut index: HashMap<u32, Vec<CellPos>> = HashMap::new();
+        for (b_idx, b) in boards.iter_mut().enumerate() {
+            b.set_patterns(&patterns);
+            for (row_idx, row) in b.grid.iter().enumerate() {
+                for (col_idx, mn) in row.iter().enumerate() {
+                    index
+                        .entry(mn.n)
+                        .or_default()
+                        .push((b_idx, row_idx, col_idx));
+                }
             }
         }
+
+        Game {
+            boards,
+            patterns,
+            index,
+        }
+    }
+
+    /// Marks `n` everywhere and returns the boards it made winners, in board
+    /// order, along with the pattern each one won with.
+    fn draw(&mut self, n: u32) -> Vec<(usize, usize)> {
+        let mut winners = vec![];
+        for &(b_idx, row, col) in self.index.get(&n).into_iter().flatten() {
+            if let Some(pattern) = self.boards[b_idx].mark_cell(row, col) {
+                winners.push((b_idx, pattern));
+            }
+        }
+
+        winners
+    }
+
+    /// Takes back a draw of `n`. Only valid for the latest draw that marked
+    /// anything, so undo draws in reverse order.
+    fn undraw(&mut self, n: u32) {
+        for &(b_idx, row, col) in self.index.get(&n).into_iter().flatten() {
+            self.boards[b_idx].unmark_cell(row, col);
+        }
     }
 }
 
-fn part2(input: &str) {
-    let (nums, boards) = parse_input(input);
-    let mut last_result = 0;
-    let mut completed_ids = vec![];
-    let mut boards = boards.into_iter().enumerate().collect::<Vec<_>>();
+/// The grid with marked numbers starred.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.grid.iter() {
+            for (col_idx, mn) in row.iter().enumerate() {
+                let sep = if col_idx == 0 { "" } else { " " };
+                let mark = if mn.marked { "*" } else { " " };
+                write!(f, "{}{:>3}{}", sep, mn.n, mark)?;
+            }
+            writeln!(f)?;
+        }
 
-    for n in nums {
-        for (id, b) in boards.iter_mut() {
-            b.mark(n);
-            if b.is_winner() {
-                last_result = b.unmarked_sum() * n;
-                completed_ids.push(*id);
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    BadNumber(String),
+    /// A board, counted from 0, with no rows.
+    EmptyBoard {
+        board: usize,
+    },
+    /// A row, counted from 0, with a different length than the board's first.
+    RaggedRow {
+        board: usize,
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::BadNumber(s) => write!(f, "invalid number {:?}", s),
+            ParseError::EmptyBoard { board } => write!(f, "board {} is empty", board),
+            ParseError::RaggedRow {
+                board,
+                row,
+                len,
+                expected,
+            } => write!(
+                f,
+                "board {}: row {} has {} numbers, expected {}",
+                board, row, len, expected
+            ),
+        }
+    }
+}
+
+/// When and how a board won. Draws are counted from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WinResult {
+    board: usize,
+    draw_idx: usize,
+    number: u32,
+    score: u32,
+    pattern: Pattern,
+}
+
+impl fmt::Display for WinResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "board {} won on draw {} (number {}) by {} with score {}",
+            self.board, self.draw_idx, self.number, self.pattern, self.score
+        )
+    }
+}
+
+/// Every board's win, in the order they happened. Boards that win on the
+/// same draw are ranked by board id.
+#[derive(Debug)]
+struct Timeline {
+    ranking: Vec<WinResult>,
+    /// Position in `ranking` of each board's win, if it ever won.
+    by_board: Vec<Option<usize>>,
+}
+
+impl Timeline {
+    /// Plays the draws until every board has won or the numbers run out.
+    fn simulate(mut game: Game, nums: &[u32]) -> Timeline {
+        let mut ranking = vec![];
+        let mut by_board = vec![None; game.boards.len()];
+
+        for (draw_idx, &n) in nums.iter().enumerate() {
+            if ranking.len() == game.boards.len() {
+                break;
+            }
+
+            for (id, pattern) in game.draw(n) {
+                by_board[id] = Some(ranking.len());
+                ranking.push(WinResult {
+                    board: id,
+                    draw_idx,
+                    number: n,
+                    score: game.boards[id].unmarked_sum() * n,
+                    pattern: game.patterns[pattern].clone(),
+                });
             }
         }
 
-        boards = boards
-            .into_iter()
-            .filter(|&(b_idx, _)| !completed_ids.contains(&b_idx))
-            .collect();
+        Timeline { ranking, by_board }
+    }
 
-        if boards.len() == 0 {
-            break;
+    fn first(&self) -> Option<&WinResult> {
+        self.ranking.first()
+    }
+
+    fn last(&self) -> Option<&WinResult> {
+        self.ranking.last()
+    }
+
+    /// The `k`-th board to win, counting from 1.
+    fn kth(&self, k: usize) -> Option<&WinResult> {
+        self.ranking.get(k.checked_sub(1)?)
+    }
+
+    fn board(&self, id: usize) -> Option<&WinResult> {
+        self.ranking.get((*self.by_board.get(id)?)?)
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = String::from("rank,board,draw,number,score,pattern\n");
+        for (idx, w) in self.ranking.iter().enumerate() {
+            out += &format!(
+                "{},{},{},{},{},{}\n",
+                idx + 1,
+                w.board,
+                w.draw_idx,
+                w.number,
+                w.score,
+                w.pattern
+            );
         }
+
+        out
+    }
+}
+
+fn print_result(label: &str, result: Option<&WinResult>) {
+    match result {
+        Some(w) => println!("{}: {}", label, w),
+        None => println!("{}: no winner", label),
+    }
+}
+
+fn parse_number(s: &str) -> Result<u32, ParseError> {
+    s.trim()
+        .parse::<u32>()
+        .map_err(|_| ParseError::BadNumber(s.to_string()))
+}
+
+fn parse_board(board: usize, s: &str) -> Result<Board, ParseError> {
+    let grid = s
+        .trim()
+        .split('\n')
+        .map(|row| {
+            row.split_whitespace()
+                .map(|ss| {
+                    Ok(MarkableNum {
+                        n: parse_number(ss)?,
+                        marked: false,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let expected = grid.first().map_or(0, |row| row.len());
+    if expected == 0 {
+        return Err(ParseError::EmptyBoard { board });
+    }
+    if let Some((row, r)) = grid.iter().enumerate().find(|(_, r)| r.len() != expected) {
+        return Err(ParseError::RaggedRow {
+            board,
+            row,
+            len: r.len(),
+            expected,
+        });
     }
 
-    println!("Part 2: {}", last_result);
+    Ok(Board::new(grid))
 }
 
-fn parse_input(input: &str) -> (Vec<u32>, Vec<Board>) {
+fn parse_input(input: &str) -> Result<(Vec<u32>, Vec<Board>), ParseError> {
     let mut input_iter = input.split("\n\n");
     let numbers = input_iter
         .next()
         .unwrap()
-        .split(",")
-        .map(|s| s.parse::<u32>().unwrap())
-        .collect::<Vec<_>>();
+        .split(',')
+        .map(parse_number)
+        .collect::<Result<Vec<_>, _>>()?;
     let boards = input_iter
-        .map(|s| Board {
-            grid: s
-                .trim()
-                .split("\n")
-                .map(|row| {
-                    row.trim()
-                        .split_whitespace()
-                        .map(|ss| MarkableNum {
-                            n: ss.parse::<u32>().unwrap(),
-                            marked: false,
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>(),
-        })
-        .collect::<Vec<_>>();
-    (numbers, boards)
+        .filter(|s| !s.trim().is_empty())
+        .enumerate()
+        .map(|(idx, s)| parse_board(idx, s))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((numbers, boards))
 }
 
 fn main() {
     let input = fs::read_to_string("aoc4.txt").expect("");
-    part1(&input);
-    part2(&input);
-}
\ No newline at end of file
+    let (nums, boards) = parse_input(&input).unwrap_or_else(|err| {
+        eprintln!("aoc4.txt: {}", err);
+        std::process::exit(1);
+    });
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let flag_value = |flag: &str| {
+        let idx = args.iter().position(|arg| arg == flag)?;
+        Some(args.get(idx + 1).map_or("", String::as_str))
+    };
+
+    let patterns = flag_value("--patterns")
+        .map(|list| {
+            list.split(',')
+                .map(|s| s.parse::<Pattern>())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                })
+        })
+        .unwrap_or_else(|| vec![Pattern::Line]);
+
+    let game = Game::new(boards, patterns);
+
+    let flag_number = |flag: &str| {
+        flag_value(flag).map(|s| {
+            s.parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("{} needs a number, got {:?}.", flag, s);
+                std::process::exit(1);
+            })
+        })
+    };
+
+    if args.first().map(String::as_str) == Some("montecarlo") {
+        let trials = flag_number("--trials").unwrap_or(10_000).max(1) as usize;
+        let seed = flag_number("--seed").unwrap_or(0);
+        let odds = montecarlo::estimate(&game, trials, &mut montecarlo::Rng::new(seed));
+        montecarlo::print_table(&odds, trials, seed);
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("live") {
+        caller::run(game, std::io::stdin().lock());
+        return;
+    }
+
+    let timeline = Timeline::simulate(game, &nums);
+    println!("Part 1: {}", timeline.first().map_or(0, |w| w.score));
+    println!("Part 2: {}", timeline.last().map_or(0, |w| w.score));
+
+    if let Some(k) = flag_number("--rank") {
+        print_result(&format!("Winner #{}", k), timeline.kth(k as usize));
+    }
+    if let Some(id) = flag_number("--board") {
+        print_result(&format!("Board {}", id), timeline.board(id as usize));
+    }
+    if let Some(fname) = flag_value("--csv") {
+        fs::write(fname, timeline.to_csv()).expect("Unable to write ranking.");
+    }
+}
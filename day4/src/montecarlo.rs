@@ -0,0 +1,134 @@
+//! Estimates how likely each board is to win first or last when the numbers
+//! come out in a random order.
+//!
+//! Every trial shuffles all numbers found on the boards, so every board wins
+//! eventually. Boards that win on the same draw share the trial, each getting
+//! an equal fraction of it, rather than being ranked by board id as in
+//! `Timeline`.
+
+use std::collections::BTreeSet;
+
+use crate::{Game, Timeline, WinResult};
+
+/// SplitMix64, small and good enough for shuffling. The same seed always
+/// gives the same sequence.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, rejecting the biased tail.
+    fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// A proportion with its 95% Wilson score interval.
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    pub p: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Estimate {
+    fn new(hits: f64, trials: usize) -> Self {
+        const Z: f64 = 1.96;
+        let n = trials as f64;
+        let p = hits / n;
+        let denom = 1.0 + Z * Z / n;
+        let centre = (p + Z * Z / (2.0 * n)) / denom;
+        let spread = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denom;
+        Estimate {
+            p,
+            low: (centre - spread).max(0.0),
+            high: (centre + spread).min(1.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BoardOdds {
+    pub first: Estimate,
+    pub last: Estimate,
+}
+
+/// Splits one win between every board that won on the same draw as `win`.
+fn credit(hits: &mut [f64], timeline: &Timeline, win: &WinResult) {
+    let tied = timeline
+        .ranking
+        .iter()
+        .filter(|w| w.draw_idx == win.draw_idx)
+        .collect::<Vec<_>>();
+    for w in tied.iter() {
+        hits[w.board] += 1.0 / tied.len() as f64;
+    }
+}
+
+/// Plays `trials` games on shuffled draws. `trials` must be non-zero.
+pub fn estimate(game: &Game, trials: usize, rng: &mut Rng) -> Vec<BoardOdds> {
+    let mut pool = game
+        .boards
+        .iter()
+        .flat_map(|b| b.grid.iter().flatten().map(|mn| mn.n))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let mut firsts = vec![0.0; game.boards.len()];
+    let mut lasts = vec![0.0; game.boards.len()];
+    for _ in 0..trials {
+        rng.shuffle(&mut pool);
+        let timeline = Timeline::simulate(game.clone(), &pool);
+        if let Some(w) = timeline.first() {
+            credit(&mut firsts, &timeline, w);
+        }
+        if let Some(w) = timeline.last() {
+            credit(&mut lasts, &timeline, w);
+        }
+    }
+
+    firsts
+        .iter()
+        .zip(lasts.iter())
+        .map(|(&first, &last)| BoardOdds {
+            first: Estimate::new(first, trials),
+            last: Estimate::new(last, trials),
+        })
+        .collect()
+}
+
+pub fn print_table(odds: &[BoardOdds], trials: usize, seed: u64) {
+    println!("{} trials, seed {}, 95% intervals", trials, seed);
+    println!("board    first  interval             last  interval");
+    for (id, o) in odds.iter().enumerate() {
+        println!(
+            "{:>5}  {:>7.4}  [{:.4}, {:.4}]  {:>7.4}  [{:.4}, {:.4}]",
+            id, o.first.p, o.first.low, o.first.high, o.last.p, o.last.low, o.last.high
+        );
+    }
+}
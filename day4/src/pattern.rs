@@ -0,0 +1,145 @@
+//! Win conditions as sets of cells that all need marking.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// Any full row or column, the classic rule.
+    Line,
+    /// Either main diagonal. Square boards only.
+    Diagonal,
+    FourCorners,
+    /// Both diagonals at once. Square boards only.
+    X,
+    /// The middle row and middle column. Boards with odd sides only.
+    Plus,
+    Blackout,
+    /// A hand-drawn mask, rows separated by `/` and marked cells as `1`,
+    /// e.g. `10001/01010/00100/01010/10001`. Boards of that size only.
+    Custom(Vec<Vec<bool>>),
+}
+
+impl Pattern {
+    /// Cell sets (as `row * cols + col`) that each complete this pattern on a
+    /// `rows` x `cols` board. Patterns that don't fit the board have none.
+    pub fn masks(&self, rows: usize, cols: usize) -> Vec<Vec<usize>> {
+        let cell = |row: usize, col: usize| row * cols + col;
+        let square = rows == cols;
+        match self {
+            Pattern::Line => {
+                let row_masks = (0..rows).map(|r| (0..cols).map(|c| cell(r, c)).collect());
+                let col_masks = (0..cols).map(|c| (0..rows).map(|r| cell(r, c)).collect());
+                row_masks.chain(col_masks).collect()
+            }
+            Pattern::Diagonal if square => vec![
+                (0..rows).map(|i| cell(i, i)).collect(),
+                (0..rows).map(|i| cell(i, cols - 1 - i)).collect(),
+            ],
+            Pattern::FourCorners => {
+                let mut corners = vec![
+                    cell(0, 0),
+                    cell(0, cols - 1),
+                    cell(rows - 1, 0),
+                    cell(rows - 1, cols - 1),
+                ];
+                corners.sort_unstable();
+                corners.dedup();
+                vec![corners]
+            }
+            Pattern::X if square => {
+                let mut cells = (0..rows)
+                    .flat_map(|i| [cell(i, i), cell(i, cols - 1 - i)])
+                    .collect::<Vec<_>>();
+                cells.sort_unstable();
+                cells.dedup();
+                vec![cells]
+            }
+            Pattern::Plus if rows % 2 == 1 && cols % 2 == 1 => {
+                let (mid_row, mid_col) = (rows / 2, cols / 2);
+                let mut cells = (0..cols)
+                    .map(|c| cell(mid_row, c))
+                    .chain((0..rows).map(|r| cell(r, mid_col)))
+                    .collect::<Vec<_>>();
+                cells.sort_unstable();
+                cells.dedup();
+                vec![cells]
+            }
+            Pattern::Blackout => vec![(0..rows * cols).collect()],
+            Pattern::Custom(mask) if mask.len() == rows && mask[0].len() == cols => {
+                let cells = (0..rows)
+                    .flat_map(|r| (0..cols).map(move |c| (r, c)))
+                    .filter(|&(r, c)| mask[r][c])
+                    .map(|(r, c)| cell(r, c))
+                    .collect::<Vec<_>>();
+                vec![cells]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Line => write!(f, "line"),
+            Pattern::Diagonal => write!(f, "diagonal"),
+            Pattern::FourCorners => write!(f, "corners"),
+            Pattern::X => write!(f, "x"),
+            Pattern::Plus => write!(f, "plus"),
+            Pattern::Blackout => write!(f, "blackout"),
+            Pattern::Custom(mask) => {
+                let rows = mask
+                    .iter()
+                    .map(|row| row.iter().map(|&m| if m { '1' } else { '0' }).collect())
+                    .collect::<Vec<String>>();
+                write!(f, "custom:{}", rows.join("/"))
+            }
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(Pattern::Line),
+            "diagonal" => Ok(Pattern::Diagonal),
+            "corners" => Ok(Pattern::FourCorners),
+            "x" => Ok(Pattern::X),
+            "plus" => Ok(Pattern::Plus),
+            "blackout" => Ok(Pattern::Blackout),
+            _ => {
+                let spec = s
+                    .strip_prefix("custom:")
+                    .ok_or_else(|| format!("Unknown pattern {:?}.", s))?;
+                let mask = spec
+                    .split('/')
+                    .map(|row| {
+                        row.chars()
+                            .map(|ch| match ch {
+                                '1' => Ok(true),
+                                '0' => Ok(false),
+                                _ => Err(format!("Invalid mask cell {:?} in {:?}.", ch, s)),
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let cols = mask[0].len();
+                if cols == 0 || mask.iter().any(|row| row.len() != cols) {
+                    return Err(format!(
+                        "Mask rows in {:?} must be the same, non-zero length.",
+                        s
+                    ));
+                }
+                if !mask.iter().flatten().any(|&m| m) {
+                    return Err(format!("Mask {:?} has no cells to mark.", s));
+                }
+
+                Ok(Pattern::Custom(mask))
+            }
+        }
+    }
+}
@@ -1,4 +1,9 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+mod sweep;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 struct Point {
@@ -16,23 +21,156 @@ impl Point {
     fn vec_to(&self, other: &Point) -> Vector {
         Vector {
             x: other.x as i32 - self.x as i32,
-            y: other.y as i32 - self.y as i32
+            y: other.y as i32 - self.y as i32,
+        }
+    }
+
+    fn offset(&self, x: i32, y: i32) -> Point {
+        Point {
+            x: (self.x as i64 + x as i64) as u32,
+            y: (self.y as i64 + y as i64) as u32,
         }
     }
 }
 
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
 impl Vector {
-    fn normalize(&mut self) {
-        if self.x != 0 {
-            self.x /= self.x.abs();
+    /// Divides out the common factor, leaving the shortest step between
+    /// lattice points along this direction, and returns that factor.
+    fn reduce(&mut self) -> u32 {
+        let g = gcd(self.x.unsigned_abs(), self.y.unsigned_abs());
+        self.x /= g as i32;
+        self.y /= g as i32;
+        g
+    }
+}
+
+/// Largest coordinate a segment may use, so the difference between any two
+/// points fits a `Vector`.
+const MAX_COORD: u32 = i32::MAX as u32;
+
+#[derive(Debug, PartialEq, Eq)]
+enum SegmentError {
+    /// Both ends are the same point, so the segment has no direction.
+    Degenerate(Point),
+    OutOfRange(u32),
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SegmentError::Degenerate(p) => {
+                write!(f, "segment starts and ends at {},{}", p.x, p.y)
+            }
+            SegmentError::OutOfRange(value) => {
+                write!(f, "coordinate {} is above {}", value, MAX_COORD)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    Malformed { line: usize },
+    BadNumber { line: usize, text: String },
+    Segment { line: usize, err: SegmentError },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Malformed { line } => {
+                write!(f, "line {}: expected \"x1,y1 -> x2,y2\"", line)
+            }
+            ParseError::BadNumber { line, text } => {
+                write!(f, "line {}: invalid coordinate {:?}", line, text)
+            }
+            ParseError::Segment { line, err } => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+/// How to turn a segment into grid points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Raster {
+    /// Only points the segment passes through exactly.
+    Lattice,
+    /// One point per step along the longer axis, nearest to the segment.
+    Bresenham,
+}
+
+impl FromStr for Raster {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lattice" => Ok(Raster::Lattice),
+            "bresenham" => Ok(Raster::Bresenham),
+            _ => Err(format!(
+                "Unknown raster mode {:?}, expected \"lattice\" or \"bresenham\".",
+                s
+            )),
+        }
+    }
+}
+
+/// How to count overlap points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Counter {
+    /// Rasterize every segment and tally the points.
+    Points,
+    /// Work on the segments themselves. Axis-aligned and diagonal only.
+    Sweep,
+}
+
+impl FromStr for Counter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "points" => Ok(Counter::Points),
+            "sweep" => Ok(Counter::Sweep),
+            _ => Err(format!(
+                "Unknown counter {:?}, expected \"points\" or \"sweep\".",
+                s
+            )),
         }
+    }
+}
+
+/// Where `Counter::Points` tallies points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// One `u16` per cell of the segments' bounding box.
+    Dense,
+    Sparse,
+}
 
-        if self.y != 0 {
-            self.y /= self.y.abs();
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dense" => Ok(Backend::Dense),
+            "sparse" => Ok(Backend::Sparse),
+            _ => Err(format!(
+                "Unknown backend {:?}, expected \"dense\" or \"sparse\".",
+                s
+            )),
         }
     }
 }
 
+/// Largest bounding box, in cells, the dense backend will allocate.
+const DENSE_LIMIT: u64 = 1 << 24;
+
+/// A segment between two distinct points, both ends included.
 #[derive(Debug)]
 struct Line {
     p1: Point,
@@ -40,92 +178,274 @@ struct Line {
 }
 
 impl Line {
+    fn new(p1: Point, p2: Point) -> Result<Line, SegmentError> {
+        if let Some(&value) = [p1.x, p1.y, p2.x, p2.y].iter().find(|&&c| c > MAX_COORD) {
+            return Err(SegmentError::OutOfRange(value));
+        }
+        if p1 == p2 {
+            return Err(SegmentError::Degenerate(p1));
+        }
+
+        Ok(Line { p1, p2 })
+    }
+
+    /// Lattice points on the segment, from `p1` to `p2`. Steps by the
+    /// direction with its gcd divided out, so any slope ends on `p2`.
     fn as_points(&self) -> Vec<Point> {
         let mut v = self.p1.vec_to(&self.p2);
-        v.normalize();
+        let steps = v.reduce();
+
+        (0..=steps as i32)
+            .map(|i| self.p1.offset(v.x * i, v.y * i))
+            .collect()
+    }
+
+    /// Bresenham's line from `p1` to `p2`. Matches `as_points` for
+    /// horizontal, vertical and 45° segments.
+    fn bresenham(&self) -> Vec<Point> {
+        let v = self.p1.vec_to(&self.p2);
+        let (dx, dy) = (v.x.abs() as i64, -(v.y.abs() as i64));
+        let (sx, sy) = (v.x.signum(), v.y.signum());
 
         let mut p = self.p1;
+        let mut err = dx + dy;
         let mut points = vec![p];
-        loop {
-            p.x = (p.x as i32 + v.x) as u32;
-            p.y = (p.y as i32 + v.y) as u32;
-            points.push(p);
-
-            if p == self.p2 {
-                break;
+        while p != self.p2 {
+            let e2 = 2 * err;
+            let (mut step_x, mut step_y) = (0, 0);
+            if e2 >= dy {
+                err += dy;
+                step_x = sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                step_y = sy;
             }
+            p = p.offset(step_x, step_y);
+            points.push(p);
         }
-        
+
         points
     }
 
+    fn rasterize(&self, raster: Raster) -> Vec<Point> {
+        match raster {
+            Raster::Lattice => self.as_points(),
+            Raster::Bresenham => self.bresenham(),
+        }
+    }
+
     fn is_horizontal(&self) -> bool {
         self.p1.x == self.p2.x || self.p1.y == self.p2.y
     }
 }
 
-fn part1(input: &str) {
-    let lines = parse_input(input)
-        .into_iter()
-        .filter(|l| l.is_horizontal())
-        .collect::<Vec<Line>>();
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{} -> {},{}",
+            self.p1.x, self.p1.y, self.p2.x, self.p2.y
+        )
+    }
+}
+
+/// Smallest and largest corners of the box holding every segment. Both
+/// raster modes stay inside it.
+fn bounds(lines: &[&Line]) -> Option<(Point, Point)> {
+    let points = lines.iter().flat_map(|l| [l.p1, l.p2]);
+    let min_x = points.clone().map(|p| p.x).min()?;
+    let min_y = points.clone().map(|p| p.y).min()?;
+    let max_x = points.clone().map(|p| p.x).max()?;
+    let max_y = points.map(|p| p.y).max()?;
+
+    Some((Point { x: min_x, y: min_y }, Point { x: max_x, y: max_y }))
+}
+
+fn count_sparse(lines: &[&Line], raster: Raster) -> usize {
     let mut grid_hm: HashMap<Point, u32> = HashMap::new();
+    for line in lines {
+        for point in line.rasterize(raster) {
+            *grid_hm.entry(point).or_insert(0) += 1;
+        }
+    }
 
+    grid_hm.values().filter(|&&count| count >= 2).count()
+}
+
+/// Counts saturate, since only reaching 2 matters.
+fn count_dense(lines: &[&Line], raster: Raster, min: Point, max: Point) -> usize {
+    let width = (max.x - min.x) as usize + 1;
+    let height = (max.y - min.y) as usize + 1;
+    let mut grid = vec![0u16; width * height];
     for line in lines {
-        for point in line.as_points() {
-            let hm_entry = grid_hm.entry(point).or_insert(0);
-            (*hm_entry) += 1;
+        for point in line.rasterize(raster) {
+            let idx = (point.y - min.y) as usize * width + (point.x - min.x) as usize;
+            grid[idx] = grid[idx].saturating_add(1);
         }
     }
 
-    let overlaps = grid_hm
-        .iter()
-        .filter(|&(_, &count)| count >= 2)
-        .collect::<Vec<_>>();
+    grid.iter().filter(|&&count| count >= 2).count()
+}
+
+/// Number of points covered by at least two lines. Without a backend, uses
+/// the dense grid when the bounding box is within `DENSE_LIMIT` cells.
+fn count_points(
+    lines: &[&Line],
+    raster: Raster,
+    backend: Option<Backend>,
+) -> Result<usize, String> {
+    let (min, max) = match bounds(lines) {
+        Some(corners) => corners,
+        None => return Ok(0),
+    };
+    let cells = (max.x - min.x + 1) as u64 * (max.y - min.y + 1) as u64;
+    let fits = cells <= DENSE_LIMIT;
+
+    match backend {
+        Some(Backend::Dense) if !fits => Err(format!(
+            "a {} cell grid is too big for the dense backend",
+            cells
+        )),
+        Some(Backend::Dense) => Ok(count_dense(lines, raster, min, max)),
+        None if fits => Ok(count_dense(lines, raster, min, max)),
+        Some(Backend::Sparse) | None => Ok(count_sparse(lines, raster)),
+    }
+}
 
-    println!("Part 1: {}", overlaps.len());
+fn count_overlaps(
+    lines: &[&Line],
+    raster: Raster,
+    counter: Counter,
+    backend: Option<Backend>,
+) -> Result<usize, String> {
+    match counter {
+        Counter::Points => count_points(lines, raster, backend),
+        Counter::Sweep => sweep::count_overlaps(lines).map_err(|err| err.to_string()),
+    }
 }
 
-fn part2(input: &str) {
-    let lines = parse_input(input);
-    let mut points_hm: HashMap<Point, u32> = HashMap::new();
-    for line in lines {
-        for point in line.as_points() {
-            let hm_entry = points_hm.entry(point).or_insert(0);
-            (*hm_entry) += 1;
+/// Times every way of counting part 2 over `rounds` runs each.
+fn bench(lines: &[Line], raster: Raster, rounds: u32) {
+    let lines = lines.iter().collect::<Vec<_>>();
+    let counters = [
+        ("sparse", Counter::Points, Some(Backend::Sparse)),
+        ("dense", Counter::Points, Some(Backend::Dense)),
+        ("sweep", Counter::Sweep, None),
+    ];
+
+    println!("{} segments, {} rounds", lines.len(), rounds);
+    for (name, counter, backend) in counters {
+        let start = Instant::now();
+        let mut result = Ok(0);
+        for _ in 0..rounds {
+            result = count_overlaps(&lines, raster, counter, backend);
+        }
+        let per_run = start.elapsed().as_secs_f64() / rounds as f64;
+
+        match result {
+            Ok(n) => println!("{:>6}: {} overlaps in {:.3}ms/run", name, n, per_run * 1e3),
+            Err(err) => println!("{:>6}: {}", name, err),
         }
     }
+}
+
+fn print_answer(part: usize, answer: Result<usize, String>) {
+    match answer {
+        Ok(n) => println!("Part {}: {}", part, n),
+        Err(err) => println!("Part {}: {}", part, err),
+    }
+}
 
-    let overlaps = points_hm
+fn part1(lines: &[Line], raster: Raster, counter: Counter, backend: Option<Backend>) {
+    let lines = lines
         .iter()
-        .filter(|&(_, &count)| count >= 2)
+        .filter(|l| l.is_horizontal())
         .collect::<Vec<_>>();
-    
-    println!("Part 2: {}", overlaps.len());
+    print_answer(1, count_overlaps(&lines, raster, counter, backend));
+}
+
+fn part2(lines: &[Line], raster: Raster, counter: Counter, backend: Option<Backend>) {
+    let lines = lines.iter().collect::<Vec<_>>();
+    print_answer(2, count_overlaps(&lines, raster, counter, backend));
 }
 
-fn parse_input(input: &str) -> Vec<Line> {
+fn parse_point(line: usize, s: &str) -> Result<Point, ParseError> {
+    let mut nums = s.trim().split(',').map(|n| {
+        n.trim().parse::<u32>().map_err(|_| ParseError::BadNumber {
+            line,
+            text: n.trim().to_string(),
+        })
+    });
+    let point = match (nums.next(), nums.next(), nums.next()) {
+        (Some(x), Some(y), None) => Point { x: x?, y: y? },
+        _ => return Err(ParseError::Malformed { line }),
+    };
+
+    Ok(point)
+}
+
+/// Parses one segment per line. Lines in errors are numbered from 1.
+fn parse_input(input: &str) -> Result<Vec<Line>, ParseError> {
     input
         .trim()
-        .split("\n")
-        .map(|line| {
-            let mut points = line.split("->").map(|ns_str| {
-                let mut nums = ns_str.trim().split(",").map(|n| n.parse::<u32>().unwrap());
-                Point {
-                    x: nums.next().unwrap(),
-                    y: nums.next().unwrap(),
-                }
-            });
-            Line {
-                p1: points.next().unwrap(),
-                p2: points.next().unwrap(),
-            }
+        .lines()
+        .enumerate()
+        .map(|(idx, s)| {
+            let line = idx + 1;
+            let (p1, p2) = s.split_once("->").ok_or(ParseError::Malformed { line })?;
+            Line::new(parse_point(line, p1)?, parse_point(line, p2)?)
+                .map_err(|err| ParseError::Segment { line, err })
         })
         .collect()
 }
- 
+
 fn main() {
-    let input = std::fs::read_to_string("aoc5.txt").expect("");
-    part1(&input);
-    part2(&input);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let flag_value = |flag: &str| {
+        let idx = args.iter().position(|arg| arg == flag)?;
+        Some(args.get(idx + 1).map_or("", String::as_str))
+    };
+
+    let raster = match flag_value("--raster") {
+        Some(mode) => mode.parse::<Raster>().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => Raster::Lattice,
+    };
+    let counter = match flag_value("--counter") {
+        Some(name) => name.parse::<Counter>().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => Counter::Points,
+    };
+    let backend = flag_value("--backend").map(|name| {
+        name.parse::<Backend>().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+    });
+
+    let fname = flag_value("--input").unwrap_or("aoc5.txt");
+    let input = std::fs::read_to_string(fname).expect("Unable to read input.");
+    let lines = parse_input(&input).unwrap_or_else(|err| {
+        eprintln!("{}: {}", fname, err);
+        std::process::exit(1);
+    });
+
+    if args.iter().any(|arg| arg == "bench") {
+        let rounds = flag_value("--rounds").map_or(100, |n| {
+            n.parse::<u32>().unwrap_or_else(|_| {
+                eprintln!("--rounds needs a number, got {:?}.", n);
+                std::process::exit(1);
+            })
+        });
+        bench(&lines, raster, rounds.max(1));
+        return;
+    }
+
+    part1(&lines, raster, counter, backend);
+    part2(&lines, raster, counter, backend);
 }
@@ -0,0 +1,263 @@
+//! Counts overlap points from the segments alone, without listing the points
+//! they cover.
+//!
+//! Every segment is horizontal, vertical or diagonal, so it lies on a line
+//! `a*x + b*y = key` from one of four families. Segments on the same line
+//! overlap in whole intervals, found by sweeping their endpoints. Segments
+//! from different families meet in at most one point, found by sweeping
+//! across x and testing only segments whose x ranges overlap. A crossing
+//! that also sits inside a same-line overlap is only counted once.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::Line;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Family {
+    /// `y = key`
+    Horizontal,
+    /// `x = key`
+    Vertical,
+    /// `x - y = key`
+    Diagonal,
+    /// `x + y = key`
+    AntiDiagonal,
+}
+
+impl Family {
+    /// Coefficients `(a, b)` of `a*x + b*y = key`.
+    fn coefficients(self) -> (i64, i64) {
+        match self {
+            Family::Horizontal => (0, 1),
+            Family::Vertical => (1, 0),
+            Family::Diagonal => (1, -1),
+            Family::AntiDiagonal => (1, 1),
+        }
+    }
+
+    /// Where `(x, y)` sits in this family, as the line's key and the position
+    /// along it.
+    fn locate(self, (x, y): (i64, i64)) -> (i64, i64) {
+        let (a, b) = self.coefficients();
+        let t = match self {
+            Family::Vertical => y,
+            _ => x,
+        };
+        (a * x + b * y, t)
+    }
+}
+
+/// A segment as an interval `lo..=hi` along its line.
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    family: Family,
+    key: i64,
+    lo: i64,
+    hi: i64,
+}
+
+impl Run {
+    fn from_line(line: &Line) -> Result<Run, SweepError> {
+        let (x1, y1) = (line.p1.x as i64, line.p1.y as i64);
+        let (x2, y2) = (line.p2.x as i64, line.p2.y as i64);
+        let family = if y1 == y2 {
+            Family::Horizontal
+        } else if x1 == x2 {
+            Family::Vertical
+        } else if x2 - x1 == y2 - y1 {
+            Family::Diagonal
+        } else if x2 - x1 == y1 - y2 {
+            Family::AntiDiagonal
+        } else {
+            return Err(SweepError::Unsupported(line.to_string()));
+        };
+
+        let (key, t1) = family.locate((x1, y1));
+        let (_, t2) = family.locate((x2, y2));
+        Ok(Run {
+            family,
+            key,
+            lo: t1.min(t2),
+            hi: t1.max(t2),
+        })
+    }
+
+    fn x_range(&self) -> (i64, i64) {
+        match self.family {
+            Family::Vertical => (self.key, self.key),
+            _ => (self.lo, self.hi),
+        }
+    }
+
+    fn contains(&self, p: (i64, i64)) -> bool {
+        let (key, t) = self.family.locate(p);
+        key == self.key && (self.lo..=self.hi).contains(&t)
+    }
+
+    /// The lattice point where two runs of different families cross, if any.
+    fn crossing(&self, other: &Run) -> Option<(i64, i64)> {
+        let (a1, b1) = self.family.coefficients();
+        let (a2, b2) = other.family.coefficients();
+        let det = a1 * b2 - a2 * b1;
+        let x = self.key * b2 - other.key * b1;
+        let y = a1 * other.key - a2 * self.key;
+        if x % det != 0 || y % det != 0 {
+            return None;
+        }
+
+        let p = (x / det, y / det);
+        (self.contains(p) && other.contains(p)).then_some(p)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SweepError {
+    /// A segment that is neither axis-aligned nor diagonal.
+    Unsupported(String),
+}
+
+impl fmt::Display for SweepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepError::Unsupported(line) => write!(
+                f,
+                "sweep needs axis-aligned or diagonal segments, found {}",
+                line
+            ),
+        }
+    }
+}
+
+/// Intervals along one line covered by at least two of `runs`, in order.
+fn line_overlaps(runs: &[Run]) -> Vec<(i64, i64)> {
+    let mut events = runs
+        .iter()
+        .flat_map(|r| [(r.lo, 1), (r.hi + 1, -1)])
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let mut overlaps = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (t, delta) in events {
+        let was_overlapping = depth >= 2;
+        depth += delta;
+        match (was_overlapping, depth >= 2) {
+            (false, true) => start = t,
+            (true, false) => overlaps.push((start, t - 1)),
+            _ => {}
+        }
+    }
+
+    overlaps
+}
+
+/// Number of points covered by at least two lines.
+pub fn count_overlaps(lines: &[&Line]) -> Result<usize, SweepError> {
+    let mut runs = lines
+        .iter()
+        .map(|l| Run::from_line(l))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut by_line: HashMap<(Family, i64), Vec<Run>> = HashMap::new();
+    for run in runs.iter() {
+        by_line.entry((run.family, run.key)).or_default().push(*run);
+    }
+    let overlaps = by_line
+        .iter()
+        .map(|(&line, runs)| (line, line_overlaps(runs)))
+        .filter(|(_, intervals)| !intervals.is_empty())
+        .collect::<HashMap<_, _>>();
+
+    runs.sort_unstable_by_key(|r| r.x_range().0);
+    let mut active: Vec<Run> = vec![];
+    let mut crossings = HashSet::new();
+    for run in runs.iter() {
+        let (x_lo, _) = run.x_range();
+        active.retain(|a| a.x_range().1 >= x_lo);
+        for a in active.iter().filter(|a| a.family != run.family) {
+            crossings.extend(run.crossing(a));
+        }
+        active.push(*run);
+    }
+
+    let in_overlap = |family: Family, p: (i64, i64)| {
+        let (key, t) = family.locate(p);
+        overlaps.get(&(family, key)).is_some_and(|intervals| {
+            let idx = intervals.partition_point(|&(_, hi)| hi < t);
+            intervals.get(idx).is_some_and(|&(lo, _)| lo <= t)
+        })
+    };
+
+    let families = [
+        Family::Horizontal,
+        Family::Vertical,
+        Family::Diagonal,
+        Family::AntiDiagonal,
+    ];
+    let same_line = overlaps
+        .values()
+        .flatten()
+        .map(|&(lo, hi)| (hi - lo + 1) as usize)
+        .sum::<usize>();
+    // Crossings outside every same-line overlap are new points. Ones inside
+    // several were counted once per overlap above.
+    let total = crossings.iter().fold(same_line as i64, |acc, &p| {
+        let inside = families.iter().filter(|&&f| in_overlap(f, p)).count() as i64;
+        acc + 1 - inside
+    });
+
+    Ok(total as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::count_overlaps;
+    use crate::{count_sparse, parse_input, Raster};
+
+    /// Counts with the sweep and by listing points, and checks they agree.
+    fn both(input: &str) -> usize {
+        let lines = parse_input(input).unwrap();
+        let lines = lines.iter().collect::<Vec<_>>();
+        let swept = count_overlaps(&lines).unwrap();
+        assert_eq!(swept, count_sparse(&lines, Raster::Lattice), "{}", input);
+        swept
+    }
+
+    #[test]
+    fn sample() {
+        let input = "0,9 -> 5,9\n8,0 -> 0,8\n9,4 -> 3,4\n2,2 -> 2,1\n7,0 -> 7,4\n\
+                     6,4 -> 2,0\n0,9 -> 2,9\n3,4 -> 1,4\n0,0 -> 8,8\n5,5 -> 8,2\n";
+        assert_eq!(both(input), 12);
+    }
+
+    #[test]
+    fn collinear_overlaps() {
+        // Nested, chained and end-to-end runs on one line.
+        assert_eq!(both("0,0 -> 9,0\n2,0 -> 4,0\n3,0 -> 7,0\n9,0 -> 12,0\n"), 7);
+        assert_eq!(both("5,1 -> 5,8\n5,8 -> 5,3\n"), 6);
+        assert_eq!(both("0,0 -> 6,6\n6,6 -> 3,3\n2,4 -> 4,2\n"), 4);
+        assert_eq!(both("0,6 -> 6,0\n4,2 -> 6,0\n1,1 -> 2,2\n"), 3);
+    }
+
+    #[test]
+    fn crossings_inside_overlaps() {
+        // Both pairs overlap in an interval, and the overlaps cross at 3,2.
+        assert_eq!(both("0,2 -> 5,2\n1,2 -> 6,2\n3,0 -> 3,4\n3,1 -> 3,6\n"), 8);
+        // A crossing inside one overlap, away from the other.
+        assert_eq!(both("0,2 -> 5,2\n1,2 -> 6,2\n3,0 -> 3,4\n"), 5);
+        // Three families through one point.
+        assert_eq!(both("0,2 -> 4,2\n2,0 -> 2,4\n0,0 -> 4,4\n"), 1);
+    }
+
+    #[test]
+    fn diagonal_crossings() {
+        // x - y = 0 and x + y = 3 meet at 1.5,1.5, which isn't a point.
+        assert_eq!(both("0,0 -> 3,3\n0,3 -> 3,0\n"), 0);
+        assert_eq!(both("0,0 -> 4,4\n0,4 -> 4,0\n"), 1);
+        assert_eq!(both("0,1 -> 3,4\n0,2 -> 2,0\n"), 0);
+        // Crossings at segment ends count too.
+        assert_eq!(both("0,0 -> 4,4\n4,0 -> 4,4\n0,4 -> 4,0\n"), 3);
+    }
+}